@@ -0,0 +1,374 @@
+use crate::config::Config;
+use crate::network::{Interface, NetworkInfo};
+use crossterm::event::KeyCode;
+use ratatui::widgets::TableState;
+
+/// Views the tab bar switches between, in the order of their number keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Interfaces,
+    Routes,
+    Dns,
+    Connections,
+    Neighbors,
+}
+
+impl View {
+    pub const ALL: [View; 5] = [View::Interfaces, View::Routes, View::Dns, View::Connections, View::Neighbors];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            View::Interfaces => "Interfaces",
+            View::Routes => "Routes",
+            View::Dns => "DNS",
+            View::Connections => "Connections",
+            View::Neighbors => "Neighbors",
+        }
+    }
+}
+
+/// Panes that take keyboard focus, in `Tab` order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Interfaces,
+    Details,
+    Debug,
+    /// The table filling every view but Interfaces.
+    Table,
+    /// The policy rules above the Routes table.
+    Rules,
+}
+
+/// UI state that outlives a single frame: which view is shown, which pane
+/// has focus, which interface is selected, whether its full-screen detail
+/// view is open and how far each pane is scrolled.
+#[derive(Debug)]
+pub struct App {
+    pub view: View,
+    pub pane: Pane,
+    /// Whether the selected interface fills the screen; its scroll offset is
+    /// `details_scroll`.
+    pub detail: bool,
+    /// The selected interface's name, so the selection stays on it when
+    /// interfaces before it appear or disappear.
+    selected: Option<String>,
+    selected_index: usize,
+    pub interface_table: TableState,
+    pub details_scroll: usize,
+    pub debug_scroll: usize,
+    pub rules_scroll: usize,
+    /// Scroll offset of each view's table, so switching views keeps it.
+    pub table_scrolls: [usize; View::ALL.len()],
+    /// Rows each pane showed at the last draw, used as the PgUp/PgDn step.
+    page_sizes: [usize; 5],
+    debug_panel: bool,
+    pub quit: bool,
+}
+
+impl App {
+    pub fn new(config: &Config) -> Self {
+        App {
+            view: View::Interfaces,
+            pane: Pane::Interfaces,
+            detail: false,
+            selected: None,
+            selected_index: 0,
+            interface_table: TableState::default(),
+            details_scroll: 0,
+            debug_scroll: 0,
+            rules_scroll: 0,
+            table_scrolls: [0; View::ALL.len()],
+            page_sizes: [1; 5],
+            debug_panel: config.panels.debug,
+            quit: false,
+        }
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode, info: &NetworkInfo) {
+        if let KeyCode::Char(digit @ '1'..='9') = code {
+            if let Some(view) = View::ALL.get(digit as usize - '1' as usize) {
+                self.show(*view);
+            }
+            return;
+        }
+
+        if self.detail {
+            match code {
+                KeyCode::Char('q') => self.quit = true,
+                KeyCode::Esc | KeyCode::Enter | KeyCode::Backspace => {
+                    self.detail = false;
+                    self.details_scroll = 0;
+                }
+                code => self.navigate(code, Pane::Details, info),
+            }
+            return;
+        }
+
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Tab => self.pane = self.next_pane(1),
+            KeyCode::BackTab => self.pane = self.next_pane(-1),
+            KeyCode::Enter if self.view == View::Interfaces && self.selected.is_some() => {
+                self.detail = true;
+                self.details_scroll = 0;
+            }
+            code => self.navigate(code, self.pane, info),
+        }
+    }
+
+    fn navigate(&mut self, code: KeyCode, pane: Pane, info: &NetworkInfo) {
+        let page = self.page_size(pane) as isize;
+        let delta = match code {
+            KeyCode::Down | KeyCode::Char('j') => 1,
+            KeyCode::Up | KeyCode::Char('k') => -1,
+            KeyCode::PageDown => page,
+            KeyCode::PageUp => -page,
+            KeyCode::Home => isize::MIN / 2,
+            KeyCode::End => isize::MAX / 2,
+            _ => return,
+        };
+        self.scroll(info, pane, delta);
+    }
+
+    /// Switches to `view`, closing the detail view and focusing the view's
+    /// first pane.
+    fn show(&mut self, view: View) {
+        self.view = view;
+        self.detail = false;
+        self.pane = self.panes()[0];
+    }
+
+    /// Re-finds the selected interface after `info` changed, falling back to
+    /// the one now at its position if it went away.
+    pub fn sync(&mut self, info: &NetworkInfo) {
+        let index = self
+            .selected
+            .as_ref()
+            .and_then(|name| info.interfaces.iter().position(|interface| &interface.name == name))
+            .unwrap_or_else(|| self.selected_index.min(info.interfaces.len().saturating_sub(1)));
+        self.selected_index = index;
+        self.selected = info.interfaces.get(index).map(|interface| interface.name.clone());
+        self.interface_table.select(self.selected.as_ref().map(|_| index));
+    }
+
+    pub fn selected_interface<'a>(&self, info: &'a NetworkInfo) -> Option<&'a Interface> {
+        let name = self.selected.as_ref()?;
+        info.interfaces.iter().find(|interface| &interface.name == name)
+    }
+
+    pub fn page_size(&self, pane: Pane) -> usize {
+        self.page_sizes[pane as usize]
+    }
+
+    pub fn set_page_size(&mut self, pane: Pane, rows: usize) {
+        self.page_sizes[pane as usize] = rows.max(1);
+    }
+
+    /// Moves the selection in the interface list, or scrolls the focused
+    /// pane. Scroll offsets are clamped when the pane is drawn, since only
+    /// then is its content known.
+    fn scroll(&mut self, info: &NetworkInfo, pane: Pane, delta: isize) {
+        let offset = match pane {
+            Pane::Interfaces => {
+                if info.interfaces.is_empty() {
+                    return;
+                }
+                let last = info.interfaces.len() - 1;
+                let index = self.selected_index.saturating_add_signed(delta).min(last);
+                if index != self.selected_index {
+                    self.selected_index = index;
+                    self.selected = Some(info.interfaces[index].name.clone());
+                    self.details_scroll = 0;
+                }
+                return;
+            }
+            Pane::Details => &mut self.details_scroll,
+            Pane::Debug => &mut self.debug_scroll,
+            Pane::Table => &mut self.table_scrolls[self.view as usize],
+            Pane::Rules => &mut self.rules_scroll,
+        };
+        *offset = offset.saturating_add_signed(delta);
+    }
+
+    /// The panes of the current view, in `Tab` order.
+    fn panes(&self) -> &'static [Pane] {
+        match (self.view, self.debug_panel) {
+            (View::Interfaces, true) => &[Pane::Interfaces, Pane::Details, Pane::Debug],
+            (View::Interfaces, false) => &[Pane::Interfaces, Pane::Details],
+            (View::Routes, true) => &[Pane::Table, Pane::Rules, Pane::Debug],
+            (View::Routes, false) => &[Pane::Table, Pane::Rules],
+            (_, true) => &[Pane::Table, Pane::Debug],
+            (_, false) => &[Pane::Table],
+        }
+    }
+
+    fn next_pane(&self, step: isize) -> Pane {
+        let panes = self.panes();
+        let current = panes.iter().position(|pane| *pane == self.pane).unwrap_or(0) as isize;
+        panes[(current + step).rem_euclid(panes.len() as isize) as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::get_local_network_info;
+    use crate::network::scripted::ScriptedCollector;
+
+    fn info(names: &[&str]) -> NetworkInfo {
+        let links = names.iter().enumerate().map(|(index, name)| Interface::new(*name, index as u32 + 1)).collect();
+        get_local_network_info(&ScriptedCollector::new(links), &Config::default()).unwrap()
+    }
+
+    fn app(info: &NetworkInfo) -> App {
+        let mut app = App::new(&Config::default());
+        app.sync(info);
+        app
+    }
+
+    fn press(app: &mut App, info: &NetworkInfo, codes: &[KeyCode]) {
+        for code in codes {
+            app.handle_key(*code, info);
+        }
+    }
+
+    fn selected(app: &App, info: &NetworkInfo) -> Option<String> {
+        app.selected_interface(info).map(|interface| interface.name.clone())
+    }
+
+    #[test]
+    fn selection_clamps_at_both_ends() {
+        let info = info(&["eth0", "eth1", "wlan0"]);
+        let mut app = app(&info);
+        assert_eq!(selected(&app, &info).as_deref(), Some("eth0"));
+
+        press(&mut app, &info, &[KeyCode::Char('k'), KeyCode::Up]);
+        assert_eq!(selected(&app, &info).as_deref(), Some("eth0"));
+
+        press(&mut app, &info, &[KeyCode::Char('j'), KeyCode::Down, KeyCode::Char('j'), KeyCode::Down]);
+        assert_eq!(selected(&app, &info).as_deref(), Some("wlan0"));
+
+        press(&mut app, &info, &[KeyCode::Up]);
+        assert_eq!(selected(&app, &info).as_deref(), Some("eth1"));
+        press(&mut app, &info, &[KeyCode::Home]);
+        assert_eq!(selected(&app, &info).as_deref(), Some("eth0"));
+        press(&mut app, &info, &[KeyCode::End]);
+        assert_eq!(selected(&app, &info).as_deref(), Some("wlan0"));
+    }
+
+    #[test]
+    fn no_interfaces_means_no_selection() {
+        let info = info(&[]);
+        let mut app = app(&info);
+
+        press(&mut app, &info, &[KeyCode::Down, KeyCode::PageDown, KeyCode::Enter]);
+        assert_eq!(selected(&app, &info), None);
+        assert!(!app.detail);
+    }
+
+    #[test]
+    fn pages_step_by_the_drawn_rows() {
+        let info = info(&["eth0", "eth1", "eth2", "eth3", "eth4"]);
+        let mut app = app(&info);
+        app.set_page_size(Pane::Interfaces, 2);
+
+        press(&mut app, &info, &[KeyCode::PageDown]);
+        assert_eq!(selected(&app, &info).as_deref(), Some("eth2"));
+        press(&mut app, &info, &[KeyCode::PageDown, KeyCode::PageDown]);
+        assert_eq!(selected(&app, &info).as_deref(), Some("eth4"));
+        press(&mut app, &info, &[KeyCode::PageUp]);
+        assert_eq!(selected(&app, &info).as_deref(), Some("eth2"));
+
+        press(&mut app, &info, &[KeyCode::Char('2')]);
+        app.set_page_size(Pane::Table, 10);
+        press(&mut app, &info, &[KeyCode::PageDown, KeyCode::PageDown, KeyCode::PageUp]);
+        assert_eq!(app.table_scrolls[View::Routes as usize], 10);
+        press(&mut app, &info, &[KeyCode::PageUp, KeyCode::PageUp]);
+        assert_eq!(app.table_scrolls[View::Routes as usize], 0);
+        assert_eq!(app.table_scrolls[View::Interfaces as usize], 0);
+    }
+
+    #[test]
+    fn tab_cycles_through_the_view_panes() {
+        let info = info(&["eth0"]);
+        let mut app = app(&info);
+        let focus = |app: &mut App, code| {
+            app.handle_key(code, &info);
+            app.pane
+        };
+
+        assert_eq!(app.pane, Pane::Interfaces);
+        assert_eq!(focus(&mut app, KeyCode::Tab), Pane::Details);
+        assert_eq!(focus(&mut app, KeyCode::Tab), Pane::Debug);
+        assert_eq!(focus(&mut app, KeyCode::Tab), Pane::Interfaces);
+        assert_eq!(focus(&mut app, KeyCode::BackTab), Pane::Debug);
+
+        assert_eq!(focus(&mut app, KeyCode::Char('2')), Pane::Table);
+        assert_eq!(focus(&mut app, KeyCode::Tab), Pane::Rules);
+        assert_eq!(focus(&mut app, KeyCode::Tab), Pane::Debug);
+        assert_eq!(focus(&mut app, KeyCode::Tab), Pane::Table);
+        assert_eq!(focus(&mut app, KeyCode::Char('3')), Pane::Table);
+        assert_eq!(focus(&mut app, KeyCode::Tab), Pane::Debug);
+
+        let mut config = Config::default();
+        config.panels.debug = false;
+        let mut app = App::new(&config);
+        assert_eq!(focus(&mut app, KeyCode::Tab), Pane::Details);
+        assert_eq!(focus(&mut app, KeyCode::Tab), Pane::Interfaces);
+        assert_eq!(focus(&mut app, KeyCode::Char('4')), Pane::Table);
+        assert_eq!(focus(&mut app, KeyCode::Tab), Pane::Table);
+    }
+
+    #[test]
+    fn selection_follows_the_interface_across_syncs() {
+        let before = info(&["eth0", "eth1", "wlan0"]);
+        let mut app = app(&before);
+        press(&mut app, &before, &[KeyCode::Down, KeyCode::Down]);
+
+        // An interface before the selected one goes away.
+        let after = info(&["eth1", "wlan0"]);
+        app.sync(&after);
+        assert_eq!(selected(&app, &after).as_deref(), Some("wlan0"));
+        assert_eq!(app.interface_table.selected(), Some(1));
+
+        // The selected interface itself goes away.
+        let after = info(&["eth1", "tun0"]);
+        app.sync(&after);
+        assert_eq!(selected(&app, &after).as_deref(), Some("tun0"));
+
+        let after = info(&["eth1"]);
+        app.sync(&after);
+        assert_eq!(selected(&app, &after).as_deref(), Some("eth1"));
+        assert_eq!(app.interface_table.selected(), Some(0));
+    }
+
+    #[test]
+    fn enter_opens_the_detail_view_and_esc_closes_it() {
+        let info = info(&["eth0", "eth1"]);
+        let mut app = app(&info);
+
+        press(&mut app, &info, &[KeyCode::Down, KeyCode::Enter]);
+        assert!(app.detail);
+        assert_eq!(selected(&app, &info).as_deref(), Some("eth1"));
+
+        // Movement scrolls the detail view instead of changing the selection.
+        press(&mut app, &info, &[KeyCode::Down, KeyCode::Down]);
+        assert_eq!(app.details_scroll, 2);
+        assert_eq!(selected(&app, &info).as_deref(), Some("eth1"));
+
+        press(&mut app, &info, &[KeyCode::Esc]);
+        assert!(!app.detail);
+        assert_eq!(app.details_scroll, 0);
+        assert!(!app.quit);
+
+        press(&mut app, &info, &[KeyCode::Enter, KeyCode::Char('2')]);
+        assert!(!app.detail);
+        assert_eq!(app.view, View::Routes);
+        // Enter only opens interface details.
+        press(&mut app, &info, &[KeyCode::Enter]);
+        assert!(!app.detail);
+
+        press(&mut app, &info, &[KeyCode::Esc]);
+        assert!(app.quit);
+    }
+}
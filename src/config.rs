@@ -0,0 +1,503 @@
+use crate::cli::{parse_duration, Args};
+use crate::network::{parse_proxy, GeoIpPaths, Provider, DEFAULT_HISTORY_SIZE, DEFAULT_PROVIDERS, DEFAULT_STUN_SERVERS};
+use anyhow::{bail, Context, Result};
+use reqwest::Url;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(500);
+const DEFAULT_PUBLIC_IP_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    /// Bytes with 1024-based prefixes.
+    #[default]
+    Binary,
+    /// Bytes with 1000-based prefixes.
+    Decimal,
+    /// Bits per second for rates, 1000-based bytes for totals.
+    Bits,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Default,
+    Mono,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Panels {
+    pub public_ip: bool,
+    pub stun: bool,
+    pub graphs: bool,
+    pub debug: bool,
+}
+
+impl Default for Panels {
+    fn default() -> Self {
+        Panels {
+            public_ip: true,
+            stun: true,
+            graphs: true,
+            debug: true,
+        }
+    }
+}
+
+/// Settings shared by collection and rendering, built from the config file
+/// and then the command line, which takes precedence.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub interval: Duration,
+    pub history: usize,
+    pub include_loopback: bool,
+    pub public_ip: bool,
+    /// Time between public IP lookups after the first.
+    pub public_ip_interval: Duration,
+    pub interfaces: Vec<String>,
+    pub exclude: Vec<String>,
+    pub providers: Vec<Provider>,
+    /// Number of providers asked concurrently for a majority answer, or
+    /// `None` to take the first provider that answers.
+    pub consensus: Option<usize>,
+    /// Whether the public IP is also looked up from each interface.
+    pub interface_public_ip: bool,
+    /// Proxy for HTTP providers instead of `HTTPS_PROXY`/`HTTP_PROXY`.
+    pub proxy: Option<Url>,
+    /// PEM bundles of root certificates trusted in addition to the system
+    /// ones, for proxies that intercept HTTPS.
+    pub ca_certs: Vec<PathBuf>,
+    /// Interface whose address public IP lookups are sent from.
+    pub bind_interface: Option<String>,
+    /// `host[:port]` of the STUN servers asked for our UDP mapping; an empty
+    /// list skips the STUN probe.
+    pub stun_servers: Vec<String>,
+    pub geoip: GeoIpPaths,
+    /// Whether interface and public addresses are named via PTR lookups.
+    pub reverse_dns: bool,
+    /// Resolver for PTR lookups instead of the first system nameserver.
+    pub resolver: Option<SocketAddr>,
+    pub aliases: HashMap<String, String>,
+    pub units: Units,
+    pub theme: Theme,
+    pub panels: Panels,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            interval: DEFAULT_INTERVAL,
+            history: DEFAULT_HISTORY_SIZE,
+            include_loopback: false,
+            public_ip: true,
+            public_ip_interval: DEFAULT_PUBLIC_IP_INTERVAL,
+            interfaces: Vec::new(),
+            exclude: Vec::new(),
+            providers: DEFAULT_PROVIDERS.iter().map(|url| Provider::new(url)).collect(),
+            consensus: None,
+            interface_public_ip: false,
+            proxy: None,
+            ca_certs: Vec::new(),
+            bind_interface: None,
+            stun_servers: DEFAULT_STUN_SERVERS.iter().map(|server| server.to_string()).collect(),
+            geoip: GeoIpPaths::default(),
+            reverse_dns: true,
+            resolver: None,
+            aliases: HashMap::new(),
+            units: Units::default(),
+            theme: Theme::default(),
+            panels: Panels::default(),
+        }
+    }
+}
+
+/// Contents of `config.toml`. Every key is optional and unknown keys are
+/// rejected so typos are reported instead of silently ignored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(deserialize_with = "deserialize_interval")]
+    interval: Option<Duration>,
+    #[serde(deserialize_with = "deserialize_history")]
+    history: Option<usize>,
+    include_loopback: Option<bool>,
+    public_ip: Option<bool>,
+    #[serde(deserialize_with = "deserialize_interval")]
+    public_ip_interval: Option<Duration>,
+    interfaces: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    providers: Option<Vec<Provider>>,
+    #[serde(deserialize_with = "deserialize_consensus")]
+    consensus: Option<usize>,
+    interface_public_ip: Option<bool>,
+    #[serde(deserialize_with = "deserialize_proxy")]
+    proxy: Option<Url>,
+    ca_certs: Option<Vec<PathBuf>>,
+    bind_interface: Option<String>,
+    stun_servers: Option<Vec<String>>,
+    geoip: Option<GeoIpPaths>,
+    reverse_dns: Option<bool>,
+    #[serde(deserialize_with = "deserialize_resolver")]
+    resolver: Option<SocketAddr>,
+    aliases: HashMap<String, String>,
+    units: Option<Units>,
+    theme: Option<Theme>,
+    panels: Option<Panels>,
+}
+
+impl Config {
+    /// Loads the config file named by `--config`, or the per-user default if
+    /// it exists, then applies command-line overrides.
+    pub fn load(args: &Args) -> Result<Self> {
+        let mut config = Config::default();
+
+        let path = match &args.config {
+            Some(path) => Some(path.clone()),
+            None => default_path().filter(|path| path.exists()),
+        };
+        if let Some(path) = path {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read config file {}", path.display()))?;
+            let file: ConfigFile = toml::from_str(&content)
+                .with_context(|| format!("Invalid config file {}", path.display()))?;
+            config.apply_file(file)
+                .with_context(|| format!("Invalid config file {}", path.display()))?;
+        }
+
+        config.apply_args(args);
+        Ok(config)
+    }
+
+    fn apply_file(&mut self, file: ConfigFile) -> Result<()> {
+        if let Some(interval) = file.interval {
+            self.interval = interval;
+        }
+        if let Some(history) = file.history {
+            self.history = history;
+        }
+        if let Some(include_loopback) = file.include_loopback {
+            self.include_loopback = include_loopback;
+        }
+        if let Some(public_ip) = file.public_ip {
+            self.public_ip = public_ip;
+        }
+        if let Some(public_ip_interval) = file.public_ip_interval {
+            self.public_ip_interval = public_ip_interval;
+        }
+        if let Some(interfaces) = file.interfaces {
+            self.interfaces = interfaces;
+        }
+        if let Some(exclude) = file.exclude {
+            self.exclude = exclude;
+        }
+        if let Some(providers) = file.providers {
+            if providers.is_empty() {
+                bail!("`providers` must list at least one provider");
+            }
+            self.providers = providers;
+        }
+        if let Some(consensus) = file.consensus {
+            self.consensus = Some(consensus);
+        }
+        if let Some(interface_public_ip) = file.interface_public_ip {
+            self.interface_public_ip = interface_public_ip;
+        }
+        if let Some(proxy) = file.proxy {
+            self.proxy = Some(proxy);
+        }
+        if let Some(ca_certs) = file.ca_certs {
+            self.ca_certs = ca_certs;
+        }
+        if let Some(bind_interface) = file.bind_interface {
+            self.bind_interface = Some(bind_interface);
+        }
+        if let Some(stun_servers) = file.stun_servers {
+            self.stun_servers = stun_servers;
+        }
+        if let Some(geoip) = file.geoip {
+            self.geoip = geoip;
+        }
+        if let Some(reverse_dns) = file.reverse_dns {
+            self.reverse_dns = reverse_dns;
+        }
+        if let Some(resolver) = file.resolver {
+            self.resolver = Some(resolver);
+        }
+        self.aliases.extend(file.aliases);
+        if let Some(units) = file.units {
+            self.units = units;
+        }
+        if let Some(theme) = file.theme {
+            self.theme = theme;
+        }
+        if let Some(panels) = file.panels {
+            self.panels = panels;
+        }
+        Ok(())
+    }
+
+    fn apply_args(&mut self, args: &Args) {
+        if let Some(interval) = args.interval {
+            self.interval = interval;
+        }
+        if let Some(history) = args.history {
+            self.history = history as usize;
+        }
+        self.include_loopback = (self.include_loopback || args.include_loopback) && !args.no_include_loopback;
+        self.public_ip &= !args.no_public_ip;
+        self.reverse_dns &= !args.no_reverse_dns;
+        self.interface_public_ip = (self.interface_public_ip || args.interface_public_ip) && !args.no_interface_public_ip;
+        if let Some(public_ip_interval) = args.public_ip_interval {
+            self.public_ip_interval = public_ip_interval;
+        }
+        if let Some(consensus) = args.consensus {
+            self.consensus = Some(consensus as usize);
+        }
+        if let Some(proxy) = &args.proxy {
+            self.proxy = Some(proxy.clone());
+        }
+        if !args.ca_certs.is_empty() {
+            self.ca_certs = args.ca_certs.clone();
+        }
+        if let Some(bind_interface) = &args.bind_interface {
+            self.bind_interface = Some(bind_interface.clone());
+        }
+        if !args.interfaces.is_empty() {
+            self.interfaces = args.interfaces.clone();
+        }
+        if !args.exclude.is_empty() {
+            self.exclude = args.exclude.clone();
+        }
+    }
+
+    /// Whether an interface passes the loopback, include and exclude filters.
+    pub fn shows_interface(&self, name: &str, loopback: bool) -> bool {
+        if loopback && !self.include_loopback {
+            return false;
+        }
+        if !self.interfaces.is_empty() && !self.interfaces.iter().any(|pattern| glob_match(pattern, name)) {
+            return false;
+        }
+        !self.exclude.iter().any(|pattern| glob_match(pattern, name))
+    }
+
+    /// The name to show for an interface, taking aliases into account.
+    pub fn display_name(&self, name: &str) -> String {
+        match self.aliases.get(name) {
+            Some(alias) => format!("{} ({})", alias, name),
+            None => name.to_string(),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/myip/config.toml`, falling back to
+/// `~/.config/myip/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("myip").join("config.toml"))
+}
+
+fn deserialize_interval<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_duration(&value).map(Some).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_history<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<usize>, D::Error> {
+    let value = usize::deserialize(deserializer)?;
+    if value < 2 {
+        return Err(serde::de::Error::custom("history must keep at least 2 samples"));
+    }
+    Ok(Some(value))
+}
+
+/// Accepts `ip`, `ip:port` or `[ipv6]:port`, defaulting to port 53.
+fn deserialize_resolver<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<SocketAddr>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    if let Ok(address) = value.parse::<IpAddr>() {
+        return Ok(Some(SocketAddr::new(address, 53)));
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom(format!("invalid resolver address '{}'", value)))
+}
+
+fn deserialize_proxy<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Url>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_proxy(&value).map(Some).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_consensus<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<usize>, D::Error> {
+    let value = usize::deserialize(deserializer)?;
+    if value < 2 {
+        return Err(serde::de::Error::custom("consensus needs at least 2 providers"));
+    }
+    Ok(Some(value))
+}
+
+/// Matches `name` against a shell-style pattern where `*` matches any run of
+/// characters and `?` matches exactly one.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn from_toml(content: &str) -> Result<Config> {
+        let mut config = Config::default();
+        config.apply_file(toml::from_str(content)?)?;
+        Ok(config)
+    }
+
+    fn error(content: &str) -> String {
+        format!("{:#}", from_toml(content).unwrap_err())
+    }
+
+    #[test]
+    fn args_override_the_file() {
+        let mut config = from_toml(
+            r#"
+            interval = "2s"
+            history = 30
+            include_loopback = true
+            interface_public_ip = true
+            interfaces = ["eth*"]
+            consensus = 3
+            "#,
+        )
+        .unwrap();
+        let args = Args::parse_from([
+            "myip",
+            "--interval",
+            "250ms",
+            "--no-include-loopback",
+            "--no-interface-public-ip",
+            "--interface",
+            "wl*,eth0",
+        ]);
+        config.apply_args(&args);
+
+        assert_eq!(config.interval, Duration::from_millis(250));
+        assert_eq!(config.history, 30);
+        assert!(!config.include_loopback);
+        assert!(!config.interface_public_ip);
+        assert_eq!(config.interfaces, ["wl*", "eth0"]);
+        assert_eq!(config.consensus, Some(3));
+    }
+
+    #[test]
+    fn args_without_overrides_keep_the_file() {
+        let mut config = from_toml("include_loopback = true\npublic_ip = false\n").unwrap();
+        config.apply_args(&Args::parse_from(["myip"]));
+
+        assert!(config.include_loopback);
+        assert!(!config.public_ip);
+        assert!(config.reverse_dns);
+        assert_eq!(config.interval, DEFAULT_INTERVAL);
+    }
+
+    #[test]
+    fn unknown_keys_are_named() {
+        assert!(error("intervall = \"1s\"\n").contains("unknown field `intervall`"));
+        assert!(error("[panels]\ngraph = false\n").contains("unknown field `graph`"));
+    }
+
+    #[test]
+    fn invalid_values_are_named() {
+        let cases = [
+            ("interval = \"2d\"\n", "interval", "unknown duration unit 'd'"),
+            ("public_ip_interval = \"0s\"\n", "public_ip_interval", "duration '0s' must be greater than zero"),
+            ("history = 1\n", "history", "history must keep at least 2 samples"),
+            ("consensus = 1\n", "consensus", "consensus needs at least 2 providers"),
+            ("resolver = \"dns.example\"\n", "resolver", "invalid resolver address 'dns.example'"),
+        ];
+        for (content, key, message) in cases {
+            let error = error(content);
+            assert!(error.contains(key), "{:?} does not name {}", error, key);
+            assert!(error.contains(message), "{:?} does not contain {:?}", error, message);
+        }
+        assert!(error("providers = []\n").contains("`providers` must list at least one provider"));
+    }
+
+    #[test]
+    fn resolver_defaults_to_port_53() {
+        let config = from_toml("resolver = \"192.0.2.53\"\n").unwrap();
+        assert_eq!(config.resolver, Some("192.0.2.53:53".parse().unwrap()));
+
+        let config = from_toml("resolver = \"[2001:db8::53]:5353\"\n").unwrap();
+        assert_eq!(config.resolver, Some("[2001:db8::53]:5353".parse().unwrap()));
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("eth0", "eth0"));
+        assert!(!glob_match("eth0", "eth01"));
+        assert!(glob_match("eth*", "eth0"));
+        assert!(glob_match("eth*", "eth"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("wl?0", "wlp0"));
+        assert!(!glob_match("wl?0", "wl0"));
+        assert!(glob_match("*-*-br", "docker-1-br"));
+        assert!(glob_match("v*h*", "vethabc"));
+        assert!(!glob_match("v*h*x", "vethabc"));
+        assert!(!glob_match("", "lo"));
+    }
+
+    #[test]
+    fn interface_filters() {
+        let mut config = from_toml("interfaces = [\"eth*\", \"lo\"]\nexclude = [\"eth1\"]\n").unwrap();
+
+        assert!(config.shows_interface("eth0", false));
+        assert!(!config.shows_interface("eth1", false));
+        assert!(!config.shows_interface("wlan0", false));
+        assert!(!config.shows_interface("lo", true));
+
+        config.include_loopback = true;
+        assert!(config.shows_interface("lo", true));
+    }
+
+    #[test]
+    fn display_names_use_aliases() {
+        let config = from_toml("[aliases]\nwlp3s0 = \"wifi\"\n").unwrap();
+
+        assert_eq!(config.display_name("wlp3s0"), "wifi (wlp3s0)");
+        assert_eq!(config.display_name("eth0"), "eth0");
+    }
+}
@@ -1,32 +1,68 @@
+mod app;
+mod cli;
+mod config;
 mod network;
+mod output;
 mod ui;
 
 use anyhow::{Context, Result};
+use clap::Parser;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use std::io;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
 use ratatui::{
     backend::CrosstermBackend,
     Terminal,
 };
 
-use crate::network::get_network_info;
+use crate::app::App;
+use crate::cli::Args;
+use crate::config::Config;
+use crate::network::{get_local_network_info, host_interfaces, spawn_updates, Collector, LinkWatcher, SystemCollector};
 use crate::ui::render_ui;
 
+const FRAME_INTERVAL: Duration = Duration::from_millis(100);
+
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
+    let args = Args::parse();
+    let config = Config::load(&args)?;
+    if args.local {
+        let interfaces = host_interfaces().context("Failed to list interfaces")?;
+        return Ok(output::print_local(&interfaces, &config, args.iface.as_deref(), args.family()));
+    }
+    let collector: Arc<dyn Collector> = Arc::new(SystemCollector::new(&config)?);
+
+    if args.json {
+        output::print_json(collector.as_ref(), &config).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    if args.plain {
+        output::print_plain(collector.as_ref(), &config).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    if args.public {
+        return output::print_public(collector.as_ref(), args.family()).await;
+    }
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let network_info = get_network_info().await.context("Failed to get network info")?;
+    let link_watcher = LinkWatcher::spawn().ok();
+    let network_info = get_local_network_info(collector.as_ref(), &config).context("Failed to get network info")?;
+    let updates = spawn_updates(collector, network_info, config.clone(), link_watcher);
 
-    let app_result = run_app(&mut terminal, network_info);
+    let app_result = run_app(&mut terminal, &config, updates);
 
     disable_raw_mode()?;
     execute!(
@@ -38,44 +74,28 @@ async fn main() -> Result<()> {
 
     if let Err(err) = app_result {
         println!("Error: {:?}", err);
+        return Ok(ExitCode::FAILURE);
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
 
 fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
-    mut network_info: network::NetworkInfo,
+    config: &Config,
+    mut updates: watch::Receiver<network::NetworkInfo>,
 ) -> Result<()> {
-    let tick_rate = std::time::Duration::from_millis(500);
-    let mut last_tick = std::time::Instant::now();
-
+    let mut app = App::new(config);
     loop {
-        let timeout = tick_rate
-            .checked_sub(last_tick.elapsed())
-            .unwrap_or_else(|| std::time::Duration::from_secs(0));
-
-        terminal.draw(|f| render_ui(f, &network_info))?;
+        terminal.draw(|f| render_ui(f, &updates.borrow_and_update(), config, &mut app))?;
 
-        if crossterm::event::poll(timeout)? {
+        if crossterm::event::poll(FRAME_INTERVAL)? {
             if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
-                    _ => {}
+                app.handle_key(key.code, &updates.borrow());
+                if app.quit {
+                    return Ok(());
                 }
             }
         }
-
-        if last_tick.elapsed() >= tick_rate {
-            tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    if let Err(e) = network::update_network_info(&mut network_info).await {
-                        eprintln!("Error updating network info: {:?}", e);
-                    }
-                })
-            });
-            
-            last_tick = std::time::Instant::now();
-        }
     }
-}
\ No newline at end of file
+}
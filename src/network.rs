@@ -1,316 +1,1396 @@
+pub mod collector;
+mod dns;
+mod egress;
+mod geoip;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+mod netlink;
+mod neighbors;
+mod public_ip;
+mod resolver;
+mod reverse_dns;
+mod routes;
+#[cfg(test)]
+pub mod scripted;
+mod sockets;
+mod stun;
+
+pub use collector::{Collector, SystemCollector};
+pub use egress::parse_proxy;
+pub use geoip::{GeoInfo, GeoIpPaths};
+pub use neighbors::Neighbor;
+pub use public_ip::{Provider, PublicIp, PublicIpHistory, DEFAULT_PROVIDERS};
+pub use resolver::ResolverConfig;
+pub use routes::{default_routes, Route, Rule};
+pub use sockets::Connection;
+pub use stun::{NatMapping, StunReport, DEFAULT_STUN_SERVERS};
+
+use public_ip::PublicLookup;
+use reverse_dns::ReverseDns;
+#[cfg(target_os = "linux")]
+pub use netlink::LinkWatcher;
+
+use crate::config::Config;
 use anyhow::Result;
-use local_ip_address::list_afinet_netifas;
-use sysinfo::{NetworkExt, NetworksExt, System, SystemExt};
+use nix::ifaddrs::getifaddrs;
+use nix::net::if_::{if_nametoindex, InterfaceFlags};
+use nix::sys::socket::SockaddrStorage;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
 use std::collections::{HashMap, VecDeque};
-use std::process::Command;
-use tokio::time::timeout;
-use std::time::Duration;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch, Notify};
+use tokio::time::MissedTickBehavior;
+use std::time::{Duration, Instant, SystemTime};
+
+pub const DEFAULT_HISTORY_SIZE: usize = 60;
+
+/// Shortest gap between public IP lookups triggered by link changes, which
+/// tend to arrive in bursts while an interface comes up.
+const MIN_PUBLIC_IP_REFRESH: Duration = Duration::from_secs(10);
+
+/// Transfer rates, in bytes per second, between two consecutive counter
+/// readings, stamped with the time of the later one.
+#[derive(Debug, Clone, Copy)]
+pub struct RateSample {
+    pub at: Instant,
+    pub rx: f64,
+    pub tx: f64,
+}
 
-const HISTORY_SIZE: usize = 60;
+#[derive(Debug, Clone, Copy)]
+struct CounterReading {
+    at: Instant,
+    rx: u64,
+    tx: u64,
+}
 
 #[derive(Debug, Clone)]
 pub struct NetworkUsage {
-    pub rx_history: VecDeque<f64>,
-    pub tx_history: VecDeque<f64>,
+    pub history: VecDeque<RateSample>,
+    pub capacity: usize,
     pub max_rx: f64,
     pub max_tx: f64,
-    #[allow(dead_code)]
-    pub last_rx: u64,
-    #[allow(dead_code)]
-    pub last_tx: u64,
+    last_reading: Option<CounterReading>,
 }
 
 impl NetworkUsage {
-    pub fn new() -> Self {
-        let mut rx_history = VecDeque::with_capacity(HISTORY_SIZE);
-        let mut tx_history = VecDeque::with_capacity(HISTORY_SIZE);
-        
-        for _ in 0..HISTORY_SIZE {
-            rx_history.push_back(0.0);
-            tx_history.push_back(0.0);
-        }
-        
+    pub fn new(capacity: usize) -> Self {
         NetworkUsage {
-            rx_history,
-            tx_history,
+            history: VecDeque::with_capacity(capacity),
+            capacity,
             max_rx: 1.0,
             max_tx: 1.0,
-            last_rx: 0,
-            last_tx: 0,
+            last_reading: None,
         }
     }
     
-    #[allow(dead_code)]
     pub fn update(&mut self, rx_bytes: u64, tx_bytes: u64) {
-        let rx_diff = if rx_bytes >= self.last_rx {
-            (rx_bytes - self.last_rx) as f64
-        } else {
-            rx_bytes as f64
+        self.update_at(rx_bytes, tx_bytes, Instant::now());
+    }
+    
+    /// Records cumulative counters read at `at`. Rates come from the real
+    /// time elapsed since the previous reading, so a late or early refresh
+    /// does not skew them. The first reading, and any reading after an
+    /// interface reset, only establishes a new baseline.
+    pub fn update_at(&mut self, rx_bytes: u64, tx_bytes: u64, at: Instant) {
+        let reading = CounterReading {
+            at,
+            rx: rx_bytes,
+            tx: tx_bytes,
         };
         
-        let tx_diff = if tx_bytes >= self.last_tx {
-            (tx_bytes - self.last_tx) as f64
-        } else {
-            tx_bytes as f64
+        let Some(last) = self.last_reading else {
+            self.last_reading = Some(reading);
+            return;
         };
         
-        self.last_rx = rx_bytes;
-        self.last_tx = tx_bytes;
-        
-        if self.rx_history.len() >= HISTORY_SIZE {
-            self.rx_history.pop_front();
-        }
-        if self.tx_history.len() >= HISTORY_SIZE {
-            self.tx_history.pop_front();
+        let elapsed = at.saturating_duration_since(last.at).as_secs_f64();
+        if elapsed <= 0.0 {
+            return;
         }
+        self.last_reading = Some(reading);
         
-        self.rx_history.push_back(rx_diff);
-        self.tx_history.push_back(tx_diff);
+        let (Some(rx_diff), Some(tx_diff)) = (counter_delta(last.rx, rx_bytes), counter_delta(last.tx, tx_bytes)) else {
+            return;
+        };
+        
+        if self.history.len() >= self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(RateSample {
+            at,
+            rx: rx_diff as f64 / elapsed,
+            tx: tx_diff as f64 / elapsed,
+        });
         
-        self.max_rx = self.rx_history.iter().copied().fold(1.0, f64::max);
-        self.max_tx = self.tx_history.iter().copied().fold(1.0, f64::max);
+        self.max_rx = self.history.iter().map(|sample| sample.rx).fold(1.0, f64::max);
+        self.max_tx = self.history.iter().map(|sample| sample.tx).fold(1.0, f64::max);
+    }
+    
+    pub fn rx_rate(&self) -> f64 {
+        self.history.back().map_or(0.0, |sample| sample.rx)
+    }
+    
+    pub fn tx_rate(&self) -> f64 {
+        self.history.back().map_or(0.0, |sample| sample.tx)
+    }
+    
+    /// Received rates over the history window, `None` until one has been
+    /// measured.
+    pub fn rx_summary(&self) -> Option<RateSummary> {
+        RateSummary::of(self.history.iter().map(|sample| sample.rx))
+    }
+    
+    pub fn tx_summary(&self) -> Option<RateSummary> {
+        RateSummary::of(self.history.iter().map(|sample| sample.tx))
+    }
+    
+    /// Seconds covered by a full history window, extrapolated from the
+    /// samples seen so far so the chart's time axis stays stable while it
+    /// fills up.
+    pub fn window_secs(&self) -> f64 {
+        match (self.history.front(), self.history.back()) {
+            (Some(first), Some(last)) if self.history.len() > 1 => {
+                let span = last.at.duration_since(first.at).as_secs_f64();
+                span * (self.capacity - 1) as f64 / (self.history.len() - 1) as f64
+            }
+            _ => 1.0,
+        }
     }
 }
 
-#[derive(Debug, Clone)]
+/// Distribution of one direction's rates, in bytes per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateSummary {
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    /// Median.
+    pub p50: f64,
+    pub p95: f64,
+}
+
+impl RateSummary {
+    fn of(rates: impl Iterator<Item = f64>) -> Option<Self> {
+        let mut rates: Vec<f64> = rates.collect();
+        if rates.is_empty() {
+            return None;
+        }
+        rates.sort_by(f64::total_cmp);
+        // Nearest-rank percentile: the smallest rate that at least `p`
+        // percent of the samples do not exceed.
+        let percentile = |p: usize| rates[((rates.len() * p + 99) / 100).max(1) - 1];
+        Some(RateSummary {
+            min: rates[0],
+            avg: rates.iter().sum::<f64>() / rates.len() as f64,
+            max: rates[rates.len() - 1],
+            p50: percentile(50),
+            p95: percentile(95),
+        })
+    }
+}
+
+/// Serializes as the latest rates only; the history is a display concern.
+/// Rates are `null` until two readings have been taken.
+impl Serialize for NetworkUsage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let latest = self.history.back();
+        let mut state = serializer.serialize_struct("NetworkUsage", 2)?;
+        state.serialize_field("rx_bytes_per_sec", &latest.map(|sample| sample.rx))?;
+        state.serialize_field("tx_bytes_per_sec", &latest.map(|sample| sample.tx))?;
+        state.end()
+    }
+}
+
+/// Difference between two readings of a cumulative counter. A drop that
+/// looks like a 32-bit counter wrapping around is bridged; any other drop
+/// means the interface was reset and yields `None`.
+fn counter_delta(previous: u64, current: u64) -> Option<u64> {
+    if current >= previous {
+        return Some(current - previous);
+    }
+    
+    const U32_RANGE: u64 = u32::MAX as u64 + 1;
+    if previous < U32_RANGE {
+        let wrapped = U32_RANGE - previous + current;
+        if wrapped < U32_RANGE / 2 {
+            return Some(wrapped);
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpFamily {
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    pub fn label(&self) -> &'static str {
+        match self {
+            IpFamily::V4 => "IPv4",
+            IpFamily::V6 => "IPv6",
+        }
+    }
+
+    pub fn matches(&self, address: &IpAddr) -> bool {
+        matches!(
+            (self, address),
+            (IpFamily::V4, IpAddr::V4(_)) | (IpFamily::V6, IpAddr::V6(_))
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AddressScope {
+    Global,
+    Private,
+    UniqueLocal,
+    LinkLocal,
+    Loopback,
+}
+
+impl AddressScope {
+    pub fn of(address: &IpAddr) -> Self {
+        match address {
+            IpAddr::V4(addr) => {
+                if addr.is_loopback() {
+                    AddressScope::Loopback
+                } else if addr.is_link_local() {
+                    AddressScope::LinkLocal
+                } else if addr.is_private() {
+                    AddressScope::Private
+                } else {
+                    AddressScope::Global
+                }
+            }
+            IpAddr::V6(addr) => {
+                let first = addr.segments()[0];
+                if addr.is_loopback() {
+                    AddressScope::Loopback
+                } else if first & 0xffc0 == 0xfe80 {
+                    AddressScope::LinkLocal
+                } else if first & 0xfe00 == 0xfc00 {
+                    AddressScope::UniqueLocal
+                } else {
+                    AddressScope::Global
+                }
+            }
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AddressScope::Global => "global",
+            AddressScope::Private => "private",
+            AddressScope::UniqueLocal => "ula",
+            AddressScope::LinkLocal => "link-local",
+            AddressScope::Loopback => "loopback",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct AddressFlags {
+    pub temporary: bool,
+    pub deprecated: bool,
+    pub tentative: bool,
+}
+
+impl AddressFlags {
+    pub fn labels(&self) -> Vec<&'static str> {
+        let mut labels = Vec::new();
+        if self.temporary {
+            labels.push("temporary");
+        }
+        if self.deprecated {
+            labels.push("deprecated");
+        }
+        if self.tentative {
+            labels.push("tentative");
+        }
+        labels
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InterfaceAddress {
+    pub address: IpAddr,
+    pub prefix_len: u8,
+    pub scope: AddressScope,
+    pub flags: AddressFlags,
+}
+
+impl fmt::Display for InterfaceAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Interface {
     pub name: String,
-    pub ipv4_addresses: Vec<String>,
-    pub ipv6_addresses: Vec<String>,
+    pub index: u32,
+    pub ipv4_addresses: Vec<InterfaceAddress>,
+    pub ipv6_addresses: Vec<InterfaceAddress>,
     pub mac_address: Option<String>,
+    #[serde(rename = "up")]
     pub status: bool,
+    #[serde(rename = "speed_mbps")]
     pub speed: Option<u64>,
     pub mtu: Option<u32>,
     pub received_bytes: u64,
     pub transmitted_bytes: u64,
+    /// Packet, error and drop counters, where the platform reports them.
+    pub packets: Option<PacketCounters>,
+    /// Interface flags as `ip link` names them, e.g. `UP`, `BROADCAST`.
+    pub link_flags: Vec<&'static str>,
+    pub driver: Option<String>,
+    #[serde(rename = "rates")]
     pub usage: NetworkUsage,
+    /// Public addresses seen from this interface's own addresses, `None`
+    /// unless per-interface lookups are enabled and the interface can reach
+    /// the internet.
+    pub public_ip: Option<PublicIp>,
 }
 
-#[derive(Debug, Clone)]
+impl Interface {
+    pub fn new(name: impl Into<String>, index: u32) -> Self {
+        Interface {
+            name: name.into(),
+            index,
+            ipv4_addresses: Vec::new(),
+            ipv6_addresses: Vec::new(),
+            mac_address: None,
+            status: false,
+            speed: None,
+            mtu: None,
+            received_bytes: 0,
+            transmitted_bytes: 0,
+            packets: None,
+            link_flags: Vec::new(),
+            driver: None,
+            usage: NetworkUsage::new(DEFAULT_HISTORY_SIZE),
+            public_ip: None,
+        }
+    }
+}
+
+/// Cumulative packet counters of one interface, including packets that were
+/// received or sent with errors and packets the kernel dropped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PacketCounters {
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+}
+
+/// Counters and link details reported by a [`Collector`] for one interface.
+/// Details a platform cannot provide are left as `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkStats {
+    pub received_bytes: u64,
+    pub transmitted_bytes: u64,
+    pub packets: Option<PacketCounters>,
+    pub mac_address: Option<String>,
+    pub mtu: Option<u32>,
+    pub speed: Option<u64>,
+    pub status: Option<bool>,
+    pub driver: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct NetworkInfo {
     pub interfaces: Vec<Interface>,
     pub hostname: String,
-    pub public_ip: Option<String>,
+    pub public_ip: PublicIp,
+    pub public_ip_history: PublicIpHistory,
+    /// UDP mapping seen by STUN servers, `None` when the probe is disabled
+    /// or has not finished yet.
+    pub stun: Option<StunReport>,
+    /// Whether the first public IP lookup is still running, so a missing
+    /// address means "not known yet" rather than "not found".
+    #[serde(skip)]
+    pub public_ip_pending: bool,
+    /// PTR names of interface and public addresses resolved so far.
+    #[serde(skip)]
+    pub hostnames: HashMap<IpAddr, String>,
+    /// Host tables behind the views other than Interfaces, refreshed with the
+    /// counters while the UI runs.
+    #[serde(skip)]
+    pub routes: Vec<Route>,
+    #[serde(skip)]
+    pub rules: Vec<Rule>,
+    #[serde(skip)]
+    pub resolver: ResolverConfig,
+    #[serde(skip)]
+    pub connections: Vec<Connection>,
+    #[serde(skip)]
+    pub neighbors: Vec<Neighbor>,
+    #[serde(skip)]
     pub debug_info: HashMap<String, Vec<String>>,
+    #[serde(skip)]
     #[allow(dead_code)]
     pub update_count: u64,
 }
 
-pub async fn get_network_info() -> Result<NetworkInfo> {
+/// A snapshot of what the host itself knows, taking no longer than reading
+/// its interfaces. The public IP and STUN mapping are left empty.
+pub fn get_local_network_info(collector: &dyn Collector, config: &Config) -> Result<NetworkInfo> {
     let hostname = gethostname::gethostname()
         .to_string_lossy()
         .to_string();
 
-    let netifs = list_afinet_netifas()?;
-    
-    let mut system = System::new_all();
-    system.refresh_all();
-    let networks = system.networks();
+    let mut interfaces = collector.links()?;
     
     let mut debug_info = HashMap::new();
-    debug_info.insert("netifs".to_string(), netifs.iter().map(|(name, _)| name.clone()).collect());
-    debug_info.insert("sysinfo".to_string(), networks.iter().map(|(name, _)| name.clone()).collect());
+    debug_info.insert("netifs".to_string(), interfaces.iter().map(|interface| interface.name.clone()).collect());
     
-    let mut interfaces = Vec::new();
-    for (name, ip) in netifs {
-        if name.starts_with("lo") || name.to_lowercase().contains("loopback") {
-            continue;
-        }
-        
-        let mut ipv4_addresses = Vec::new();
-        let mut ipv6_addresses = Vec::new();
-        
-        match ip {
-            std::net::IpAddr::V4(addr) => {
-                ipv4_addresses.push(addr.to_string());
-            }
-            std::net::IpAddr::V6(addr) => {
-                ipv6_addresses.push(addr.to_string());
-            }
-        }
-        
-        let mut rx_bytes = 0;
-        let mut tx_bytes = 0;
-        
-        let name_lower = name.to_lowercase();
-        for (net_name, stats) in networks.iter() {
-            if net_name == &name || net_name.to_lowercase() == name_lower {
-                rx_bytes = stats.received();
-                tx_bytes = stats.transmitted();
-                break;
-            }
-        }
-        
-        if rx_bytes == 0 && tx_bytes == 0 {
-            let base_name = name.chars()
-                .skip_while(|c| c.is_alphabetic())
-                .collect::<String>();
-                
-            if !base_name.is_empty() {
-                for (net_name, stats) in networks.iter() {
-                    let net_base = net_name.chars()
-                        .skip_while(|c| c.is_alphabetic())
-                        .collect::<String>();
-                        
-                    if !net_base.is_empty() && net_base == base_name {
-                        rx_bytes = stats.received();
-                        tx_bytes = stats.transmitted();
-                        break;
-                    }
-                }
-            }
-        }
-        
-        let interface = Interface {
-            name,
-            ipv4_addresses,
-            ipv6_addresses,
-            mac_address: None,
-            status: true,
-            speed: None,
-            mtu: None,
-            received_bytes: rx_bytes,
-            transmitted_bytes: tx_bytes,
-            usage: NetworkUsage::new(),
-        };
-        
-        interfaces.push(interface);
+    interfaces.retain(|interface| config.shows_interface(&interface.name, is_loopback(&interface.name)));
+    for interface in &mut interfaces {
+        interface.usage = NetworkUsage::new(config.history);
     }
     
-    let mut interfaces_with_no_stats = Vec::new();
-    for interface in &interfaces {
-        if interface.received_bytes == 0 && interface.transmitted_bytes == 0 {
-            interfaces_with_no_stats.push(interface.name.clone());
-        }
+    let stats = collector.link_stats()?;
+    apply_link_stats(&mut interfaces, &stats);
+    for interface in &mut interfaces {
+        interface.usage.update(interface.received_bytes, interface.transmitted_bytes);
     }
     
+    let mut counter_names: Vec<String> = stats.into_keys().collect();
+    counter_names.sort();
+    debug_info.insert("counters".to_string(), counter_names);
+    
+    let interfaces_with_no_stats: Vec<String> = interfaces.iter()
+        .filter(|interface| interface.received_bytes == 0 && interface.transmitted_bytes == 0)
+        .map(|interface| interface.name.clone())
+        .collect();
+    
     if !interfaces_with_no_stats.is_empty() {
-        debug_info.insert("interfaces_with_no_stats".to_string(), interfaces_with_no_stats.clone());
-        
-        #[cfg(any(target_os = "linux", target_os = "macos"))]
-        {
-            if let Ok(output) = Command::new("netstat").arg("-i").output() {
-                if let Ok(output_str) = String::from_utf8(output.stdout) {
-                    debug_info.insert("netstat_output".to_string(), vec![output_str.clone()]);
-                    
-                    for line in output_str.lines() {
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        if parts.len() >= 5 {
-                            let if_name = parts[0];
-                            
-                            for interface in &mut interfaces {
-                                if interface.name == if_name || interface.name.starts_with(if_name) {
-                                    if let Ok(rx) = parts[4].parse::<u64>() {
-                                        interface.received_bytes = rx * 1024;
-                                    }
-                                    if parts.len() >= 8 {
-                                        if let Ok(tx) = parts[7].parse::<u64>() {
-                                            interface.transmitted_bytes = tx * 1024;
-                                        }
-                                    }
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        debug_info.insert("interfaces_with_no_stats".to_string(), interfaces_with_no_stats);
     }
     
-    let public_ip = (tokio::spawn(get_real_public_ip()).await).unwrap_or_default();
-
-    
     Ok(NetworkInfo {
         interfaces,
         hostname,
-        public_ip,
+        public_ip: PublicIp::default(),
+        public_ip_history: PublicIpHistory::default(),
+        stun: None,
+        public_ip_pending: config.public_ip,
+        hostnames: HashMap::new(),
+        routes: Vec::new(),
+        rules: Vec::new(),
+        resolver: ResolverConfig::default(),
+        connections: Vec::new(),
+        neighbors: Vec::new(),
         debug_info,
         update_count: 0,
     })
 }
 
-async fn get_real_public_ip() -> Option<String> {
-    (timeout(Duration::from_secs(5), async {
-        let client = match reqwest::Client::builder()
-            .timeout(Duration::from_secs(4))
-            .build() {
-                Ok(client) => client,
-                Err(_) => return None,
+/// A full snapshot, with the public IP and STUN lookups done before it
+/// returns.
+pub async fn get_network_info(collector: &dyn Collector, config: &Config) -> Result<NetworkInfo> {
+    let mut info = get_local_network_info(collector, config)?;
+    if config.public_ip {
+        let interfaces = config.interface_public_ip.then_some(info.interfaces.as_slice());
+        PublicIpUpdate::lookup(collector, config, interfaces).await.apply(&mut info);
+    }
+    Ok(info)
+}
+
+/// Looks up the public IPv4 and IPv6 addresses concurrently, then adds
+/// location and network owner where a GeoIP database knows them.
+pub async fn lookup_public_ip(collector: &dyn Collector) -> PublicIp {
+    let (v4, v6) = futures::join!(collector.public_ip(IpFamily::V4), collector.public_ip(IpFamily::V6));
+    combine_lookups(collector, v4, v6)
+}
+
+/// Looks up the egress address of every interface that is up and has a
+/// routable address, concurrently, keyed by interface name. Families the
+/// interface has no routable address for are not looked up.
+pub async fn lookup_interface_public_ips(collector: &dyn Collector, interfaces: &[Interface]) -> HashMap<String, PublicIp> {
+    let lookups = interfaces
+        .iter()
+        .filter(|interface| interface.status && !is_loopback(&interface.name))
+        .filter_map(|interface| {
+            let routable = |addresses: &[InterfaceAddress]| {
+                addresses.iter().any(|address| {
+                    !matches!(address.scope, AddressScope::LinkLocal | AddressScope::Loopback) && !address.flags.tentative
+                })
             };
-            
-        for url in [
-            "https://api.ipify.org", 
-            "https://ifconfig.me/ip", 
-            "https://icanhazip.com",
-            "https://ipinfo.io/ip",
-            "https://myexternalip.com/raw"
-        ] {
-            match client.get(url).send().await {
-                Ok(resp) => {
-                    if resp.status().is_success() {
-                        if let Ok(text) = resp.text().await {
-                            let ip = text.trim().to_string();
-                            if !ip.is_empty() {
-                                return Some(ip);
-                            }
-                        }
+            let v4 = routable(&interface.ipv4_addresses);
+            let v6 = routable(&interface.ipv6_addresses);
+            (v4 || v6).then_some(async move {
+                let lookup = |wanted: bool, family| async move {
+                    match wanted {
+                        true => collector.interface_public_ip(&interface.name, family).await,
+                        false => PublicLookup::default(),
                     }
-                },
-                Err(_) => continue,
-            }
+                };
+                let (v4, v6) = futures::join!(lookup(v4, IpFamily::V4), lookup(v6, IpFamily::V6));
+                (interface.name.clone(), combine_lookups(collector, v4, v6))
+            })
+        });
+    futures::future::join_all(lookups).await.into_iter().collect()
+}
+
+/// Merges the lookups of both families, adding location and network owner
+/// where a GeoIP database knows them.
+fn combine_lookups(collector: &dyn Collector, v4: PublicLookup, v6: PublicLookup) -> PublicIp {
+    let mut public_ip = PublicIp {
+        v4: v4.address,
+        v6: v6.address,
+        errors: v4.errors.into_iter().chain(v6.errors).collect(),
+        disagreements: v4.disagreements.into_iter().chain(v6.disagreements).collect(),
+    };
+
+    for public in public_ip.v4.iter_mut().chain(&mut public_ip.v6).chain(&mut public_ip.disagreements) {
+        public.geo = collector.geo(public.address);
+    }
+    public_ip
+}
+
+/// Replaces the interfaces with freshly enumerated `interfaces`, keeping
+/// counters and traffic history for those that are still present.
+pub fn refresh_interfaces(info: &mut NetworkInfo, mut interfaces: Vec<Interface>, config: &Config) {
+    info.debug_info.insert("netifs".to_string(), interfaces.iter().map(|interface| interface.name.clone()).collect());
+    interfaces.retain(|interface| config.shows_interface(&interface.name, is_loopback(&interface.name)));
+    
+    for interface in &mut interfaces {
+        interface.usage = NetworkUsage::new(config.history);
+        if let Some(previous) = info.interfaces.iter().find(|previous| {
+            previous.name == interface.name && previous.index == interface.index
+        }) {
+            interface.received_bytes = previous.received_bytes;
+            interface.transmitted_bytes = previous.transmitted_bytes;
+            interface.mac_address = previous.mac_address.clone();
+            interface.mtu = previous.mtu;
+            interface.speed = previous.speed;
+            interface.packets = previous.packets;
+            interface.driver = previous.driver.clone();
+            interface.usage = previous.usage.clone();
+            interface.public_ip = previous.public_ip.clone();
         }
-        None
-    }).await).unwrap_or_default()
+    }
+    
+    info.interfaces = interfaces;
 }
 
-#[allow(dead_code)]
-pub async fn update_network_info(info: &mut NetworkInfo) -> Result<()> {
-    let mut system = System::new_all();
-    system.refresh_all();
-    let networks = system.networks();
+pub async fn update_network_info(info: &mut NetworkInfo, collector: &dyn Collector) -> Result<()> {
+    let stats = collector.link_stats()?;
+    record_link_stats(info, &stats);
+    Ok(())
+}
+
+/// Stores a counter reading and adds it to each interface's traffic history.
+fn record_link_stats(info: &mut NetworkInfo, stats: &HashMap<String, LinkStats>) {
+    apply_link_stats(&mut info.interfaces, stats);
     
     for interface in &mut info.interfaces {
-        let mut rx_bytes = 0;
-        let mut tx_bytes = 0;
+        interface.usage.update(interface.received_bytes, interface.transmitted_bytes);
+    }
+    
+    info.update_count += 1;
+}
+
+/// Runs `read` on the blocking pool, as listing links and reading counters
+/// goes through sysfs, procfs and `getifaddrs`.
+async fn read_blocking<T: Send + 'static>(
+    collector: &Arc<dyn Collector>,
+    read: impl FnOnce(&dyn Collector) -> Result<T> + Send + 'static,
+) -> Result<T> {
+    let collector = collector.clone();
+    tokio::task::spawn_blocking(move || read(collector.as_ref())).await?
+}
+
+/// The routing tables and rules, resolver configuration, sockets and
+/// neighbor cache as read by one refresh, each with what kept it from being
+/// read.
+struct Tables {
+    routes: Result<Vec<Route>>,
+    rules: Result<Vec<Rule>>,
+    resolver: Result<ResolverConfig>,
+    connections: Result<Vec<Connection>>,
+    neighbors: Result<Vec<Neighbor>>,
+}
+
+impl Tables {
+    /// Reads every table on the blocking pool, as procfs and netlink reads
+    /// can stall on a busy host.
+    async fn read(collector: &Arc<dyn Collector>) -> Result<Self> {
+        let collector = collector.clone();
+        Ok(tokio::task::spawn_blocking(move || Tables {
+            routes: collector.routes(),
+            rules: collector.rules(),
+            resolver: collector.resolver(),
+            connections: collector.connections(),
+            neighbors: collector.neighbors(),
+        })
+        .await?)
+    }
+
+    /// Stores the tables that were read, returning what failed. A table that
+    /// cannot be read keeps its last contents.
+    fn apply(self, info: &mut NetworkInfo) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut keep = |table: &str, result: Result<()>| {
+            if let Err(e) = result {
+                errors.push(format!("Error reading {}: {:#}", table, e));
+            }
+        };
+
+        keep("routes", self.routes.map(|routes| info.routes = routes));
+        keep("routing rules", self.rules.map(|rules| info.rules = rules));
+        keep("resolver configuration", self.resolver.map(|resolver| info.resolver = resolver));
+        keep("connections", self.connections.map(|connections| info.connections = connections));
+        keep("neighbors", self.neighbors.map(|neighbors| info.neighbors = neighbors));
+        errors
+    }
+}
+
+/// Re-reads the tables into `info` without blocking the runtime, returning
+/// what failed.
+async fn refresh_tables(info: &mut NetworkInfo, collector: &Arc<dyn Collector>) -> Vec<String> {
+    match Tables::read(collector).await {
+        Ok(tables) => tables.apply(info),
+        Err(e) => vec![format!("Error reading tables: {:#}", e)],
+    }
+}
+
+/// Runs collection on its own task, publishing a fresh snapshot after every
+/// refresh so the render loop never waits on a collector. Public IP lookups
+/// and the STUN probe run on a second task, at once and then every
+/// `public_ip_interval` or sooner after a link change, so slow providers
+/// never delay counter updates. Both tasks stop once every receiver has been
+/// dropped.
+pub fn spawn_updates(
+    collector: Arc<dyn Collector>,
+    mut info: NetworkInfo,
+    config: Config,
+    mut link_watcher: Option<LinkWatcher>,
+) -> watch::Receiver<NetworkInfo> {
+    // Receivers start from the snapshot as it is, without tables, so the UI
+    // can draw at once; the task fills them in before the first tick.
+    let (tx, rx) = watch::channel(info.clone());
+    let link_changed = Arc::new(Notify::new());
+    let reverse_dns = config.reverse_dns.then(|| Arc::new(ReverseDns::default()));
+    let mut public_ips = if config.public_ip {
+        Some(spawn_public_ip_refresh(collector.clone(), config.clone(), link_changed.clone()))
+    } else {
+        None
+    };
+    
+    tokio::spawn(async move {
+        let errors = refresh_tables(&mut info, &collector).await;
+        if !errors.is_empty() {
+            info.debug_info.insert("errors".to_string(), errors);
+        }
+        if tx.send(info.clone()).is_err() {
+            return;
+        }
+
+        let mut ticker = tokio::time::interval(config.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker.tick().await;
         
-        for (net_name, stats) in networks.iter() {
-            if net_name == &interface.name || net_name.to_lowercase() == interface.name.to_lowercase() {
-                rx_bytes = stats.received();
-                tx_bytes = stats.transmitted();
+        loop {
+            ticker.tick().await;
+            
+            let mut errors = Vec::new();
+            if link_watcher.as_mut().map_or(true, LinkWatcher::changed) {
+                match read_blocking(&collector, |collector| collector.links()).await {
+                    Ok(interfaces) => refresh_interfaces(&mut info, interfaces, &config),
+                    Err(e) => errors.push(format!("Error refreshing interfaces: {:#}", e)),
+                }
+                if link_watcher.is_some() {
+                    link_changed.notify_one();
+                }
+            }
+            // Polling from here on: every refresh rediscovers interfaces, and
+            // public IP lookups are left to their own interval rather than
+            // being hurried along by a change reported on every tick.
+            if link_watcher.as_ref().is_some_and(LinkWatcher::stopped) {
+                link_watcher = None;
+            }
+            if let Some(public_ips) = public_ips.as_mut() {
+                while let Ok(update) = public_ips.try_recv() {
+                    update.apply(&mut info);
+                }
+            }
+            if let Some(reverse_dns) = &reverse_dns {
+                info.hostnames = resolve_hostnames(reverse_dns, &collector, &info);
+            }
+            match read_blocking(&collector, |collector| collector.link_stats()).await {
+                Ok(stats) => record_link_stats(&mut info, &stats),
+                Err(e) => errors.push(format!("Error updating network info: {:#}", e)),
+            }
+            errors.extend(refresh_tables(&mut info, &collector).await);
+            
+            if errors.is_empty() {
+                info.debug_info.remove("errors");
+            } else {
+                info.debug_info.insert("errors".to_string(), errors);
+            }
+            
+            if tx.send(info.clone()).is_err() {
                 break;
             }
         }
-        
-        if rx_bytes == 0 && tx_bytes == 0 {
-            let name = &interface.name;
-            let base_name = name.chars()
-                .skip_while(|c| c.is_alphabetic())
-                .collect::<String>();
-                
-            if !base_name.is_empty() {
-                for (net_name, stats) in networks.iter() {
-                    let net_base = net_name.chars()
-                        .skip_while(|c| c.is_alphabetic())
-                        .collect::<String>();
-                        
-                    if !net_base.is_empty() && net_base == base_name {
-                        rx_bytes = stats.received();
-                        tx_bytes = stats.transmitted();
-                        break;
+    });
+    
+    rx
+}
+
+/// Names every interface and public address that reverse DNS has resolved so
+/// far, starting lookups for the rest.
+fn resolve_hostnames(reverse_dns: &Arc<ReverseDns>, collector: &Arc<dyn Collector>, info: &NetworkInfo) -> HashMap<IpAddr, String> {
+    let interface_addresses = info
+        .interfaces
+        .iter()
+        .flat_map(|interface| interface.ipv4_addresses.iter().chain(&interface.ipv6_addresses))
+        .map(|address| address.address);
+    let public_addresses = info
+        .interfaces
+        .iter()
+        .filter_map(|interface| interface.public_ip.as_ref())
+        .chain([&info.public_ip])
+        .flat_map(|public_ip| public_ip.v4.iter().chain(&public_ip.v6))
+        .map(|public| public.address);
+
+    interface_addresses
+        .chain(public_addresses)
+        .filter_map(|address| Some((address, reverse_dns.get(collector, address)?)))
+        .collect()
+}
+
+/// Result of one round of public IP lookups.
+struct PublicIpUpdate {
+    public_ip: PublicIp,
+    /// Per-interface egress addresses, `None` when that mode is off.
+    interfaces: Option<HashMap<String, PublicIp>>,
+    /// `None` when no STUN servers are configured.
+    stun: Option<StunReport>,
+}
+
+impl PublicIpUpdate {
+    /// Looks up the public addresses, those of `interfaces` unless `None`,
+    /// and the STUN mapping, all concurrently.
+    async fn lookup(collector: &dyn Collector, config: &Config, interfaces: Option<&[Interface]>) -> Self {
+        let per_interface = async {
+            match interfaces {
+                Some(interfaces) => Some(lookup_interface_public_ips(collector, interfaces).await),
+                None => None,
+            }
+        };
+        let stun = async {
+            match config.stun_servers.is_empty() {
+                true => None,
+                false => Some(collector.stun().await),
+            }
+        };
+        let (public_ip, interfaces, stun) = futures::join!(lookup_public_ip(collector), per_interface, stun);
+        PublicIpUpdate { public_ip, interfaces, stun }
+    }
+
+    fn apply(mut self, info: &mut NetworkInfo) {
+        info.public_ip_history.record(&self.public_ip, SystemTime::now());
+        info.public_ip = self.public_ip;
+        info.stun = self.stun;
+        info.public_ip_pending = false;
+        if let Some(per_interface) = self.interfaces.as_mut() {
+            for interface in &mut info.interfaces {
+                interface.public_ip = per_interface.remove(&interface.name);
+            }
+        }
+    }
+}
+
+/// Looks up the public IP and STUN mapping right away, then every
+/// `public_ip_interval`, or early when `link_changed` is notified, sending
+/// each result back to the update loop.
+fn spawn_public_ip_refresh(
+    collector: Arc<dyn Collector>,
+    config: Config,
+    link_changed: Arc<Notify>,
+) -> mpsc::UnboundedReceiver<PublicIpUpdate> {
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut last_lookup: Option<Instant> = None;
+        loop {
+            if let Some(last_lookup) = last_lookup {
+                tokio::select! {
+                    _ = tokio::time::sleep_until((last_lookup + config.public_ip_interval).into()) => {}
+                    _ = link_changed.notified() => {
+                        tokio::time::sleep_until((last_lookup + MIN_PUBLIC_IP_REFRESH).into()).await;
                     }
                 }
             }
+
+            last_lookup = Some(Instant::now());
+            // Enumerated afresh so an uplink that just came up is included.
+            let interfaces = match config.interface_public_ip {
+                true => read_blocking(&collector, |collector| collector.links()).await.ok().map(|mut interfaces| {
+                    interfaces.retain(|interface| config.shows_interface(&interface.name, is_loopback(&interface.name)));
+                    interfaces
+                }),
+                false => None,
+            };
+            let update = PublicIpUpdate::lookup(collector.as_ref(), &config, interfaces.as_deref()).await;
+            if tx.send(update).is_err() {
+                break;
+            }
+        }
+    });
+
+    rx
+}
+
+fn apply_link_stats(interfaces: &mut [Interface], stats: &HashMap<String, LinkStats>) {
+    for interface in interfaces.iter_mut() {
+        let Some(link) = stats.get(&interface.name) else {
+            continue;
+        };
+        interface.received_bytes = link.received_bytes;
+        interface.transmitted_bytes = link.transmitted_bytes;
+        interface.mac_address = link.mac_address.clone();
+        interface.mtu = link.mtu;
+        interface.speed = link.speed;
+        interface.packets = link.packets;
+        interface.driver = link.driver.clone();
+        if let Some(status) = link.status {
+            interface.status = status;
         }
-        
-        interface.received_bytes = rx_bytes;
-        interface.transmitted_bytes = tx_bytes;
-        
-        interface.usage.update(rx_bytes, tx_bytes);
     }
-    
-    info.update_count += 1;
-    
-    Ok(())
 }
 
+#[cfg(not(target_os = "linux"))]
+pub struct LinkWatcher;
+
+/// Without rtnetlink there is nothing to subscribe to, so every refresh
+/// rediscovers interfaces instead.
+#[cfg(not(target_os = "linux"))]
+impl LinkWatcher {
+    pub fn spawn() -> Result<Self> {
+        Ok(LinkWatcher)
+    }
+
+    pub fn changed(&mut self) -> bool {
+        true
+    }
+
+    pub fn stopped(&self) -> bool {
+        true
+    }
+}
+
+pub fn is_loopback(name: &str) -> bool {
+    name.starts_with("lo") || name.to_lowercase().contains("loopback")
+}
+
+/// Lists this host's interfaces and addresses without building a
+/// `SystemCollector`, whose public lookups need readable CA bundles and
+/// GeoIP databases.
+pub fn host_interfaces() -> Result<Vec<Interface>> {
+    #[cfg(target_os = "linux")]
+    let ipv6_flags = linux::LinuxCollector::new().ipv6_flags();
+    #[cfg(not(target_os = "linux"))]
+    let ipv6_flags = HashMap::new();
+    list_interfaces(&ipv6_flags)
+}
+
+fn list_interfaces(ipv6_flags: &HashMap<(String, Ipv6Addr), AddressFlags>) -> Result<Vec<Interface>> {
+    let entries = getifaddrs()?.map(|ifaddr| IfAddr {
+        address: ifaddr.address.as_ref().and_then(sockaddr_ip),
+        netmask: ifaddr.netmask.as_ref().and_then(sockaddr_ip),
+        name: ifaddr.interface_name,
+        flags: ifaddr.flags,
+    });
+    Ok(group_addresses(entries, ipv6_flags, |name| if_nametoindex(name).unwrap_or(0)))
+}
+
+/// One `getifaddrs` entry: a link without an address, or one of its
+/// addresses.
+struct IfAddr {
+    name: String,
+    flags: InterfaceFlags,
+    address: Option<IpAddr>,
+    netmask: Option<IpAddr>,
+}
+
+/// Folds `getifaddrs` entries into one `Interface` per name, ordered by
+/// kernel index, with each address listed once.
+fn group_addresses(
+    entries: impl IntoIterator<Item = IfAddr>,
+    ipv6_flags: &HashMap<(String, Ipv6Addr), AddressFlags>,
+    index_of: impl Fn(&str) -> u32,
+) -> Vec<Interface> {
+    let mut interfaces: Vec<Interface> = Vec::new();
+
+    for ifaddr in entries {
+        let position = match interfaces.iter().position(|i| i.name == ifaddr.name) {
+            Some(position) => position,
+            None => {
+                let index = index_of(&ifaddr.name);
+                interfaces.push(Interface::new(ifaddr.name.clone(), index));
+                interfaces.len() - 1
+            }
+        };
+        let interface = &mut interfaces[position];
+        interface.status |= ifaddr.flags.contains(InterfaceFlags::IFF_UP);
+        for (flag, label) in LINK_FLAGS {
+            if ifaddr.flags.contains(*flag) && !interface.link_flags.contains(label) {
+                interface.link_flags.push(label);
+            }
+        }
+
+        let Some(address) = ifaddr.address else {
+            continue;
+        };
+        let prefix_len = ifaddr.netmask
+            .map(|mask| prefix_len(&mask))
+            .unwrap_or(match address {
+                IpAddr::V4(_) => 32,
+                IpAddr::V6(_) => 128,
+            });
+        let flags = match address {
+            IpAddr::V6(addr) => ipv6_flags
+                .get(&(interface.name.clone(), addr))
+                .copied()
+                .unwrap_or_default(),
+            IpAddr::V4(_) => AddressFlags::default(),
+        };
+        let entry = InterfaceAddress {
+            address,
+            prefix_len,
+            scope: AddressScope::of(&address),
+            flags,
+        };
+
+        let addresses = match address {
+            IpAddr::V4(_) => &mut interface.ipv4_addresses,
+            IpAddr::V6(_) => &mut interface.ipv6_addresses,
+        };
+        if !addresses.iter().any(|existing| existing.address == entry.address) {
+            addresses.push(entry);
+        }
+    }
+
+    interfaces.sort_by_key(|interface| interface.index);
+    interfaces
+}
+
+/// Interface flags worth showing, in the order `ip link` lists them.
+const LINK_FLAGS: &[(InterfaceFlags, &str)] = &[
+    (InterfaceFlags::IFF_LOOPBACK, "LOOPBACK"),
+    (InterfaceFlags::IFF_BROADCAST, "BROADCAST"),
+    (InterfaceFlags::IFF_POINTOPOINT, "POINTOPOINT"),
+    (InterfaceFlags::IFF_MULTICAST, "MULTICAST"),
+    (InterfaceFlags::IFF_NOARP, "NOARP"),
+    (InterfaceFlags::IFF_ALLMULTI, "ALLMULTI"),
+    (InterfaceFlags::IFF_PROMISC, "PROMISC"),
+    (InterfaceFlags::IFF_UP, "UP"),
+    (InterfaceFlags::IFF_RUNNING, "RUNNING"),
+    #[cfg(target_os = "linux")]
+    (InterfaceFlags::IFF_LOWER_UP, "LOWER_UP"),
+    #[cfg(target_os = "linux")]
+    (InterfaceFlags::IFF_DORMANT, "DORMANT"),
+];
+
+fn sockaddr_ip(addr: &SockaddrStorage) -> Option<IpAddr> {
+    if let Some(sin) = addr.as_sockaddr_in() {
+        return Some(IpAddr::V4(Ipv4Addr::from(sin.ip())));
+    }
+    addr.as_sockaddr_in6().map(|sin6| IpAddr::V6(sin6.ip()))
+}
+
+fn prefix_len(mask: &IpAddr) -> u8 {
+    match mask {
+        IpAddr::V4(mask) => u32::from(*mask).count_ones() as u8,
+        IpAddr::V6(mask) => u128::from(*mask).count_ones() as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scripted::ScriptedCollector;
+    use super::stun::StunMapping;
+    use super::*;
+
+    fn ethernet() -> Interface {
+        let address: IpAddr = "192.0.2.10".parse().unwrap();
+        let mut interface = Interface::new("eth0", 2);
+        interface.status = true;
+        interface.ipv4_addresses.push(InterfaceAddress {
+            address,
+            prefix_len: 24,
+            scope: AddressScope::of(&address),
+            flags: AddressFlags::default(),
+        });
+        interface
+    }
+
+    fn stun_report() -> StunReport {
+        StunReport {
+            local_address: Some("192.0.2.10:40000".parse().unwrap()),
+            mappings: vec![StunMapping {
+                server: "stun.example:3478".to_string(),
+                mapped_address: "203.0.113.7:61000".parse().unwrap(),
+            }],
+            mapping: NatMapping::EndpointIndependent,
+            errors: Vec::new(),
+        }
+    }
+
+    fn config() -> Config {
+        Config {
+            stun_servers: vec!["stun.example:3478".to_string()],
+            ..Config::default()
+        }
+    }
+
+    /// Lets the clock move on between readings, which are stamped with
+    /// `Instant::now()`.
+    async fn next_reading(info: &mut NetworkInfo, collector: &ScriptedCollector) {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        update_network_info(info, collector).await.unwrap();
+    }
+
+    /// Feeds `readings` of `(seconds after start, rx, tx)` to a fresh usage.
+    fn usage_from(capacity: usize, readings: &[(f64, u64, u64)]) -> NetworkUsage {
+        let start = Instant::now();
+        let mut usage = NetworkUsage::new(capacity);
+        for &(secs, rx, tx) in readings {
+            usage.update_at(rx, tx, start + Duration::from_secs_f64(secs));
+        }
+        usage
+    }
+
+    fn rates(usage: &NetworkUsage) -> Vec<(f64, f64)> {
+        usage.history.iter().map(|sample| (sample.rx, sample.tx)).collect()
+    }
+
+    #[test]
+    fn first_reading_is_a_baseline() {
+        let usage = usage_from(10, &[(0.0, 1000, 1000)]);
+
+        assert!(usage.history.is_empty());
+        assert_eq!(usage.rx_rate(), 0.0);
+        assert_eq!(usage.rx_summary(), None);
+    }
+
+    #[test]
+    fn rates_use_elapsed_time() {
+        let usage = usage_from(10, &[(0.0, 0, 0), (0.5, 1000, 500), (3.5, 4000, 500), (4.0, 4100, 600)]);
+
+        assert_eq!(rates(&usage), [(2000.0, 1000.0), (1000.0, 0.0), (200.0, 200.0)]);
+        assert_eq!(usage.max_rx, 2000.0);
+    }
+
+    #[test]
+    fn reading_at_the_same_instant_is_ignored() {
+        let usage = usage_from(10, &[(0.0, 0, 0), (1.0, 100, 100), (1.0, 900, 900), (2.0, 200, 200)]);
+
+        assert_eq!(rates(&usage), [(100.0, 100.0), (100.0, 100.0)]);
+    }
+
+    #[test]
+    fn counter_wrapping_at_32_bits() {
+        let near_max = u64::from(u32::MAX) - 999;
+        let usage = usage_from(10, &[(0.0, near_max, 0), (2.0, 500, 2000)]);
+
+        assert_eq!(rates(&usage), [(750.0, 1000.0)]);
+        assert_eq!(counter_delta(near_max, 500), Some(1500));
+    }
+
+    #[test]
+    fn counter_reset_is_not_a_wrap() {
+        let usage = usage_from(10, &[(0.0, 10_000_000_000, 5000), (1.0, 100, 6000), (2.0, 1100, 7000)]);
+
+        assert_eq!(rates(&usage), [(1000.0, 1000.0)]);
+        assert_eq!(counter_delta(10_000_000_000, 100), None);
+        // Too far back for a wrap within one refresh.
+        assert_eq!(counter_delta(1000, 10), None);
+    }
+
+    #[test]
+    fn summary_over_the_window() {
+        let readings: Vec<(f64, u64, u64)> = (0..=12u64).map(|i| (i as f64, i * (i + 1) * 50, 0)).collect();
+        let usage = usage_from(10, &readings);
+
+        // Rates 100, 200, ... 1200; the window keeps the last ten.
+        assert_eq!(usage.history.len(), 10);
+        let summary = usage.rx_summary().unwrap();
+        assert_eq!(summary.min, 300.0);
+        assert_eq!(summary.max, 1200.0);
+        assert_eq!(summary.avg, 750.0);
+        assert_eq!(summary.p50, 700.0);
+        assert_eq!(summary.p95, 1200.0);
+        assert_eq!(usage.tx_summary().unwrap().max, 0.0);
+        assert_eq!(usage.window_secs(), 9.0);
+    }
+
+    #[test]
+    fn percentiles_of_a_spiky_window() {
+        let mut readings = vec![(0.0, 0, 0)];
+        let mut total = 0;
+        for i in 1..=20u64 {
+            total += if i == 7 { 100_000 } else { 1000 };
+            readings.push((i as f64, total, 0));
+        }
+        let summary = usage_from(20, &readings).rx_summary().unwrap();
+
+        assert_eq!(summary.min, 1000.0);
+        assert_eq!(summary.p50, 1000.0);
+        assert_eq!(summary.p95, 1000.0);
+        assert_eq!(summary.max, 100_000.0);
+        assert_eq!(summary.avg, (19.0 * 1000.0 + 100_000.0) / 20.0);
+    }
+
+    #[tokio::test]
+    async fn rates_follow_scripted_counters() {
+        let collector = ScriptedCollector::new(vec![Interface::new("lo", 1), ethernet()])
+            .with_counters("eth0", [(1000, 500), (3000, 1500), (7000, 2500)]);
+
+        let mut info = get_network_info(&collector, &config()).await.unwrap();
+        assert_eq!(info.interfaces.len(), 1);
+        assert_eq!(info.interfaces[0].received_bytes, 1000);
+        assert!(info.interfaces[0].usage.history.is_empty());
+
+        next_reading(&mut info, &collector).await;
+        next_reading(&mut info, &collector).await;
+        let usage = &info.interfaces[0].usage;
+        assert_eq!(usage.history.len(), 2);
+        assert!(usage.history[0].rx > 0.0);
+        let elapsed = usage.history[1].at.duration_since(usage.history[0].at).as_secs_f64();
+        assert!((usage.rx_rate() * elapsed - 4000.0).abs() < 1e-6);
+        assert!((usage.tx_rate() * elapsed - 1000.0).abs() < 1e-6);
+
+        // The sequence has run out, so the counters stand still.
+        next_reading(&mut info, &collector).await;
+        assert_eq!(info.interfaces[0].usage.rx_rate(), 0.0);
+        assert_eq!(info.interfaces[0].received_bytes, 7000);
+        assert_eq!(info.update_count, 3);
+    }
+
+    fn ifaddr(name: &str, flags: InterfaceFlags, address: Option<&str>, netmask: Option<&str>) -> IfAddr {
+        IfAddr {
+            name: name.to_string(),
+            flags,
+            address: address.map(|address| address.parse().unwrap()),
+            netmask: netmask.map(|netmask| netmask.parse().unwrap()),
+        }
+    }
+
+    fn index_of(name: &str) -> u32 {
+        match name {
+            "lo" => 1,
+            "eth0" => 2,
+            _ => 3,
+        }
+    }
+
+    #[test]
+    fn address_scopes() {
+        let scope = |address: &str| AddressScope::of(&address.parse().unwrap());
+
+        assert_eq!(scope("127.0.0.1"), AddressScope::Loopback);
+        assert_eq!(scope("169.254.10.1"), AddressScope::LinkLocal);
+        assert_eq!(scope("10.1.2.3"), AddressScope::Private);
+        assert_eq!(scope("172.16.0.1"), AddressScope::Private);
+        assert_eq!(scope("192.168.1.20"), AddressScope::Private);
+        assert_eq!(scope("172.32.0.1"), AddressScope::Global);
+        assert_eq!(scope("203.0.113.7"), AddressScope::Global);
+
+        assert_eq!(scope("::1"), AddressScope::Loopback);
+        assert_eq!(scope("fe80::1"), AddressScope::LinkLocal);
+        assert_eq!(scope("febf::1"), AddressScope::LinkLocal);
+        assert_eq!(scope("fc00::1"), AddressScope::UniqueLocal);
+        assert_eq!(scope("fd12:3456::1"), AddressScope::UniqueLocal);
+        assert_eq!(scope("fec0::1"), AddressScope::Global);
+        assert_eq!(scope("2001:db8::1"), AddressScope::Global);
+
+        assert_eq!(AddressScope::UniqueLocal.label(), "ula");
+        assert_eq!(AddressScope::LinkLocal.label(), "link-local");
+    }
+
+    #[test]
+    fn address_flag_labels() {
+        assert!(AddressFlags::default().labels().is_empty());
+        let flags = AddressFlags {
+            temporary: true,
+            deprecated: true,
+            tentative: true,
+        };
+        assert_eq!(flags.labels(), ["temporary", "deprecated", "tentative"]);
+    }
+
+    #[test]
+    fn link_flags_follow_ip_link_order() {
+        let up = InterfaceFlags::IFF_UP | InterfaceFlags::IFF_RUNNING;
+        let entries = [
+            ifaddr("eth0", InterfaceFlags::IFF_MULTICAST | InterfaceFlags::IFF_BROADCAST | up, None, None),
+            ifaddr("eth0", InterfaceFlags::IFF_BROADCAST | up, Some("192.0.2.10"), Some("255.255.255.0")),
+            ifaddr("tun0", InterfaceFlags::IFF_POINTOPOINT | InterfaceFlags::IFF_NOARP, None, None),
+        ];
+
+        let interfaces = group_addresses(entries, &HashMap::new(), index_of);
+        assert!(interfaces[0].status);
+        assert_eq!(interfaces[0].link_flags, ["BROADCAST", "MULTICAST", "UP", "RUNNING"]);
+        assert!(!interfaces[1].status);
+        assert_eq!(interfaces[1].link_flags, ["POINTOPOINT", "NOARP"]);
+    }
+
+    #[test]
+    fn addresses_are_grouped_by_interface() {
+        let up = InterfaceFlags::IFF_UP;
+        let entries = [
+            ifaddr("eth0", up, None, None),
+            ifaddr("eth0", up, Some("192.0.2.10"), Some("255.255.255.0")),
+            ifaddr("lo", up | InterfaceFlags::IFF_LOOPBACK, Some("127.0.0.1"), Some("255.0.0.0")),
+            ifaddr("eth0", up, Some("192.0.2.11"), Some("255.255.255.128")),
+            ifaddr("eth0", up, Some("2001:db8::10"), Some("ffff:ffff:ffff:ffff::")),
+            ifaddr("eth0", up, Some("fe80::1"), None),
+            ifaddr("eth0", up, Some("192.0.2.10"), Some("255.255.255.0")),
+        ];
+        let temporary = AddressFlags {
+            temporary: true,
+            ..AddressFlags::default()
+        };
+        let ipv6_flags = HashMap::from([(("eth0".to_string(), "2001:db8::10".parse().unwrap()), temporary)]);
+
+        let interfaces = group_addresses(entries, &ipv6_flags, index_of);
+        let names: Vec<&str> = interfaces.iter().map(|interface| interface.name.as_str()).collect();
+        assert_eq!(names, ["lo", "eth0"]);
+        assert_eq!(interfaces[0].index, 1);
+        assert_eq!(interfaces[0].link_flags, ["LOOPBACK", "UP"]);
+
+        let eth0 = &interfaces[1];
+        let listed = |addresses: &[InterfaceAddress]| -> Vec<String> { addresses.iter().map(ToString::to_string).collect() };
+        assert_eq!(listed(&eth0.ipv4_addresses), ["192.0.2.10/24", "192.0.2.11/25"]);
+        assert_eq!(listed(&eth0.ipv6_addresses), ["2001:db8::10/64", "fe80::1/128"]);
+        assert_eq!(eth0.ipv6_addresses[0].flags, temporary);
+        assert_eq!(eth0.ipv6_addresses[0].scope, AddressScope::Global);
+        assert_eq!(eth0.ipv6_addresses[1].flags, AddressFlags::default());
+        assert_eq!(eth0.ipv6_addresses[1].scope, AddressScope::LinkLocal);
+    }
+
+    #[tokio::test]
+    async fn grouped_addresses_reach_the_snapshot() {
+        let mut eth0 = ethernet();
+        let entries = [
+            ifaddr("eth0", InterfaceFlags::IFF_UP, Some("192.0.2.11"), Some("255.255.255.0")),
+            ifaddr("eth0", InterfaceFlags::IFF_UP, Some("2001:db8::10"), Some("ffff:ffff:ffff:ffff::")),
+        ];
+        let grouped = group_addresses(entries, &HashMap::new(), index_of).remove(0);
+        eth0.ipv4_addresses.extend(grouped.ipv4_addresses);
+        eth0.ipv6_addresses.extend(grouped.ipv6_addresses);
+        let collector = ScriptedCollector::new(vec![eth0]).with_counters("eth0", [(1000, 1000)]);
+
+        let info = get_network_info(&collector, &config()).await.unwrap();
+        assert_eq!(info.interfaces.len(), 1);
+        let addresses: Vec<String> = info.interfaces[0]
+            .ipv4_addresses
+            .iter()
+            .chain(&info.interfaces[0].ipv6_addresses)
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(addresses, ["192.0.2.10/24", "192.0.2.11/24", "2001:db8::10/64"]);
+    }
+
+    #[tokio::test]
+    async fn counter_reset_starts_a_new_baseline() {
+        let collector = ScriptedCollector::new(vec![ethernet()])
+            .with_counters("eth0", [(5_000_000_000, 5000), (100, 100), (600, 300)]);
+
+        let mut info = get_network_info(&collector, &config()).await.unwrap();
+        next_reading(&mut info, &collector).await;
+        assert!(info.interfaces[0].usage.history.is_empty());
+
+        next_reading(&mut info, &collector).await;
+        let usage = &info.interfaces[0].usage;
+        assert_eq!(usage.history.len(), 1);
+        assert!(usage.rx_rate() > usage.tx_rate());
+    }
+
+    #[tokio::test]
+    async fn public_ip_and_stun_come_from_the_collector() {
+        let collector = ScriptedCollector::new(vec![ethernet()])
+            .with_public_ip("203.0.113.7".parse().unwrap())
+            .with_public_ip("2001:db8::7".parse().unwrap())
+            .with_stun(stun_report());
+
+        let info = get_network_info(&collector, &config()).await.unwrap();
+        let v4 = info.public_ip.v4.as_ref().unwrap();
+        assert_eq!(v4.address, "203.0.113.7".parse::<IpAddr>().unwrap());
+        assert_eq!(v4.provider, "scripted");
+        assert_eq!(info.public_ip.v6.as_ref().unwrap().address, "2001:db8::7".parse::<IpAddr>().unwrap());
+        assert!(info.public_ip.errors.is_empty());
+        assert_eq!(info.public_ip_history.changes().count(), 1);
+        assert_eq!(info.stun, Some(stun_report()));
+        assert_eq!(info.stun.unwrap().mapped_address(), Some("203.0.113.7:61000".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn background_lookup_fills_the_local_snapshot() {
+        let collector = ScriptedCollector::new(vec![ethernet()])
+            .with_counters("eth0", [(1000, 500)])
+            .with_public_ip("203.0.113.7".parse().unwrap())
+            .with_stun(stun_report());
+        let config = Config {
+            interval: Duration::from_millis(10),
+            public_ip_interval: Duration::from_secs(3600),
+            ..config()
+        };
+        let info = get_local_network_info(&collector, &config).unwrap();
+        assert_eq!(info.public_ip, PublicIp::default());
+        assert_eq!(info.stun, None);
+        assert!(info.public_ip_pending);
+
+        let mut updates = spawn_updates(Arc::new(collector), info, config, None);
+        let info = tokio::time::timeout(Duration::from_secs(5), updates.wait_for(|info| info.stun.is_some()))
+            .await
+            .expect("no lookup within the first refreshes")
+            .unwrap()
+            .clone();
+        assert_eq!(info.public_ip.v4.unwrap().address, "203.0.113.7".parse::<IpAddr>().unwrap());
+        assert_eq!(info.stun, Some(stun_report()));
+        assert_eq!(info.public_ip_history.changes().count(), 1);
+        assert!(!info.public_ip_pending);
+    }
+
+    #[tokio::test]
+    async fn disabled_lookups_are_skipped() {
+        let collector = ScriptedCollector::new(vec![ethernet()])
+            .with_public_ip("203.0.113.7".parse().unwrap())
+            .with_stun(stun_report());
+
+        let no_stun = Config {
+            stun_servers: Vec::new(),
+            ..config()
+        };
+        let info = get_network_info(&collector, &no_stun).await.unwrap();
+        assert!(info.public_ip.v4.is_some());
+        assert_eq!(info.stun, None);
+
+        let offline = Config {
+            public_ip: false,
+            ..config()
+        };
+        let info = get_network_info(&collector, &offline).await.unwrap();
+        assert_eq!(info.public_ip, PublicIp::default());
+        assert_eq!(info.stun, None);
+        assert!(!info.public_ip_pending);
+    }
+}
@@ -0,0 +1,245 @@
+use super::public_ip::{get_consensus_public_ip, get_real_public_ip, Provider, PublicLookup};
+use super::dns;
+use super::egress::Egress;
+use super::geoip::{GeoInfo, GeoIp};
+use super::neighbors::Neighbor;
+use super::resolver::{read_resolver_config, ResolverConfig};
+use super::reverse_dns::lookup_ptr;
+use super::routes::{Route, Rule};
+use super::sockets::Connection;
+use super::stun::{self, StunReport};
+use super::{list_interfaces, Interface, IpFamily, LinkStats, PacketCounters};
+use crate::config::Config;
+use anyhow::Result;
+use futures::future::{BoxFuture, FutureExt};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+#[cfg(not(target_os = "linux"))]
+use std::sync::Mutex;
+
+/// Source of everything `get_network_info` and `update_network_info` know
+/// about the host: links with their addresses, per-link counters, the
+/// public address of each IP family and the host tables behind the other
+/// views.
+pub trait Collector: Send + Sync {
+    fn links(&self) -> Result<Vec<Interface>>;
+
+    fn link_stats(&self) -> Result<HashMap<String, LinkStats>>;
+
+    /// Every routing table but `local`.
+    fn routes(&self) -> Result<Vec<Route>>;
+
+    fn rules(&self) -> Result<Vec<Rule>>;
+
+    fn resolver(&self) -> Result<ResolverConfig>;
+
+    fn connections(&self) -> Result<Vec<Connection>>;
+
+    fn neighbors(&self) -> Result<Vec<Neighbor>>;
+
+    fn public_ip(&self, family: IpFamily) -> BoxFuture<'_, PublicLookup>;
+
+    /// Like `public_ip`, but sent from the address of `interface`, so the
+    /// answer is where that interface's traffic leaves for the internet.
+    fn interface_public_ip<'a>(&'a self, interface: &'a str, family: IpFamily) -> BoxFuture<'a, PublicLookup>;
+
+    fn stun(&self) -> BoxFuture<'_, StunReport>;
+
+    fn geo(&self, address: IpAddr) -> Option<GeoInfo>;
+
+    fn reverse_dns(&self, address: IpAddr) -> BoxFuture<'_, Option<String>>;
+}
+
+/// Collects from the running host: `getifaddrs` for links, procfs/sysfs on
+/// Linux or sysinfo elsewhere for counters, HTTP and DNS providers for the
+/// public IP (through the configured proxy and interface), STUN servers for
+/// the UDP mapping, local MaxMind databases for GeoIP, and the configured or
+/// system resolver for PTR names. Routes, sockets and neighbors come from
+/// procfs and rtnetlink, so only Linux has them.
+#[derive(Debug)]
+pub struct SystemCollector {
+    #[cfg(target_os = "linux")]
+    linux: super::linux::LinuxCollector,
+    #[cfg(not(target_os = "linux"))]
+    system: Mutex<sysinfo::System>,
+    providers: Vec<Provider>,
+    consensus: Option<usize>,
+    egress: Egress,
+    stun_servers: Vec<String>,
+    geoip: Option<GeoIp>,
+    resolver: SocketAddr,
+}
+
+impl SystemCollector {
+    /// Fails only if a configured CA bundle or GeoIP database cannot be read.
+    pub fn new(config: &Config) -> Result<Self> {
+        Ok(SystemCollector {
+            #[cfg(target_os = "linux")]
+            linux: super::linux::LinuxCollector::new(),
+            #[cfg(not(target_os = "linux"))]
+            system: Mutex::new(sysinfo::System::new()),
+            providers: config.providers.clone(),
+            consensus: config.consensus,
+            egress: Egress::new(config)?,
+            stun_servers: config.stun_servers.clone(),
+            geoip: GeoIp::open(&config.geoip)?,
+            resolver: config.resolver.unwrap_or_else(dns::system_resolver),
+        })
+    }
+
+    fn lookup_public_ip(&self, egress: Egress, family: IpFamily) -> BoxFuture<'_, PublicLookup> {
+        async move {
+            match self.consensus {
+                Some(count) => get_consensus_public_ip(&egress, &self.providers, family, count).await,
+                None => get_real_public_ip(&egress, &self.providers, family).await,
+            }
+        }
+        .boxed()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Collector for SystemCollector {
+    fn links(&self) -> Result<Vec<Interface>> {
+        list_interfaces(&self.linux.ipv6_flags())
+    }
+
+    fn link_stats(&self) -> Result<HashMap<String, LinkStats>> {
+        let counters = self.linux.counters()?;
+
+        Ok(counters
+            .into_iter()
+            .map(|(name, counters)| {
+                let details = self.linux.link_details(&name);
+                let stats = LinkStats {
+                    received_bytes: counters.rx_bytes,
+                    transmitted_bytes: counters.tx_bytes,
+                    packets: Some(PacketCounters {
+                        rx_packets: counters.rx_packets,
+                        tx_packets: counters.tx_packets,
+                        rx_errors: counters.rx_errors,
+                        tx_errors: counters.tx_errors,
+                        rx_dropped: counters.rx_dropped,
+                        tx_dropped: counters.tx_dropped,
+                    }),
+                    mac_address: details.mac_address,
+                    mtu: details.mtu,
+                    speed: details.speed,
+                    status: Some(details.status),
+                    driver: details.driver,
+                };
+                (name, stats)
+            })
+            .collect())
+    }
+
+    fn routes(&self) -> Result<Vec<Route>> {
+        // procfs only has the main table, but still works where rtnetlink
+        // dumps are filtered.
+        super::netlink::routes(&self.linux.table_names()).or_else(|_| self.linux.routes())
+    }
+
+    fn rules(&self) -> Result<Vec<Rule>> {
+        super::netlink::rules(&self.linux.table_names())
+    }
+
+    fn resolver(&self) -> Result<ResolverConfig> {
+        read_resolver_config(self.linux.root())
+    }
+
+    fn connections(&self) -> Result<Vec<Connection>> {
+        self.linux.connections()
+    }
+
+    fn neighbors(&self) -> Result<Vec<Neighbor>> {
+        super::netlink::neighbors()
+    }
+
+    fn public_ip(&self, family: IpFamily) -> BoxFuture<'_, PublicLookup> {
+        self.lookup_public_ip(self.egress.clone(), family)
+    }
+
+    fn interface_public_ip<'a>(&'a self, interface: &'a str, family: IpFamily) -> BoxFuture<'a, PublicLookup> {
+        self.lookup_public_ip(self.egress.bound_to(interface), family)
+    }
+
+    fn stun(&self) -> BoxFuture<'_, StunReport> {
+        stun::probe(&self.stun_servers).boxed()
+    }
+
+    fn geo(&self, address: IpAddr) -> Option<GeoInfo> {
+        self.geoip.as_ref()?.lookup(address)
+    }
+
+    fn reverse_dns(&self, address: IpAddr) -> BoxFuture<'_, Option<String>> {
+        lookup_ptr(self.resolver, address).boxed()
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Collector for SystemCollector {
+    fn links(&self) -> Result<Vec<Interface>> {
+        list_interfaces(&HashMap::new())
+    }
+
+    fn link_stats(&self) -> Result<HashMap<String, LinkStats>> {
+        use sysinfo::{NetworkExt, NetworksExt, SystemExt};
+
+        let mut system = self.system.lock().unwrap();
+        system.refresh_networks_list();
+        system.refresh_networks();
+
+        Ok(system
+            .networks()
+            .iter()
+            .map(|(name, data)| {
+                let stats = LinkStats {
+                    received_bytes: data.total_received(),
+                    transmitted_bytes: data.total_transmitted(),
+                    ..LinkStats::default()
+                };
+                (name.clone(), stats)
+            })
+            .collect())
+    }
+
+    fn routes(&self) -> Result<Vec<Route>> {
+        anyhow::bail!("not available on this platform")
+    }
+
+    fn rules(&self) -> Result<Vec<Rule>> {
+        anyhow::bail!("not available on this platform")
+    }
+
+    fn resolver(&self) -> Result<ResolverConfig> {
+        read_resolver_config(std::path::Path::new("/"))
+    }
+
+    fn connections(&self) -> Result<Vec<Connection>> {
+        anyhow::bail!("not available on this platform")
+    }
+
+    fn neighbors(&self) -> Result<Vec<Neighbor>> {
+        anyhow::bail!("not available on this platform")
+    }
+
+    fn public_ip(&self, family: IpFamily) -> BoxFuture<'_, PublicLookup> {
+        self.lookup_public_ip(self.egress.clone(), family)
+    }
+
+    fn interface_public_ip<'a>(&'a self, interface: &'a str, family: IpFamily) -> BoxFuture<'a, PublicLookup> {
+        self.lookup_public_ip(self.egress.bound_to(interface), family)
+    }
+
+    fn stun(&self) -> BoxFuture<'_, StunReport> {
+        stun::probe(&self.stun_servers).boxed()
+    }
+
+    fn geo(&self, address: IpAddr) -> Option<GeoInfo> {
+        self.geoip.as_ref()?.lookup(address)
+    }
+
+    fn reverse_dns(&self, address: IpAddr) -> BoxFuture<'_, Option<String>> {
+        lookup_ptr(self.resolver, address).boxed()
+    }
+}
@@ -0,0 +1,507 @@
+use super::resolver::read_resolver_config;
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::hash_map::RandomState;
+use std::fmt::Write;
+use std::hash::{BuildHasher, Hasher};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+pub const DNS_PORT: u16 = 53;
+
+const HEADER_LEN: usize = 12;
+const CLASS_IN: u16 = 1;
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_TRUNCATED: u16 = 0x0200;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+/// Compression pointers followed while reading one name before giving up on
+/// a response that loops.
+const MAX_POINTER_JUMPS: usize = 32;
+/// Classic DNS over UDP never exceeds 512 bytes without EDNS, which we do not
+/// advertise; the extra room tolerates servers that ignore that.
+const MAX_RESPONSE_LEN: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordType {
+    A,
+    Aaaa,
+    Txt,
+    Ptr,
+}
+
+impl RecordType {
+    fn code(self) -> u16 {
+        match self {
+            RecordType::A => 1,
+            RecordType::Aaaa => 28,
+            RecordType::Txt => 16,
+            RecordType::Ptr => 12,
+        }
+    }
+}
+
+/// Answer records we understand; anything else in a response is skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Record {
+    Address(IpAddr),
+    Txt(String),
+    /// The target of a PTR record, without the trailing dot.
+    Name(String),
+}
+
+/// The first nameserver queries end up at, as the DNS view reports it: the
+/// upstreams behind systemd-resolved's stub when known, otherwise those in
+/// `/etc/resolv.conf`.
+pub fn system_resolver() -> SocketAddr {
+    resolver_below(Path::new("/"))
+}
+
+/// [`system_resolver`] for the system below `root`, falling back to the local
+/// host as the C library does when there is no nameserver.
+fn resolver_below(root: &Path) -> SocketAddr {
+    let nameserver = read_resolver_config(root).ok().and_then(|config| {
+        // Scoped IPv6 nameservers (`fe80::1%eth0`) cannot be expressed as a
+        // plain `IpAddr`, so they are skipped.
+        config.effective_servers().iter().find_map(|server| server.parse::<IpAddr>().ok())
+    });
+    SocketAddr::new(nameserver.unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST)), DNS_PORT)
+}
+
+/// The `in-addr.arpa` or `ip6.arpa` name whose PTR record names `address`.
+pub fn reverse_name(address: IpAddr) -> String {
+    match address {
+        IpAddr::V4(address) => {
+            let [a, b, c, d] = address.octets();
+            format!("{}.{}.{}.{}.in-addr.arpa", d, c, b, a)
+        }
+        IpAddr::V6(address) => {
+            let mut name = String::with_capacity(72);
+            for byte in address.octets().iter().rev() {
+                let _ = write!(name, "{:x}.{:x}.", byte & 0x0f, byte >> 4);
+            }
+            name.push_str("ip6.arpa");
+            name
+        }
+    }
+}
+
+/// Sends one query for `name` to `resolver` over UDP and returns the answer
+/// records. The socket is bound to the resolver's address family, so asking
+/// an IPv6 resolver also tells it our IPv6 address.
+pub async fn query(resolver: SocketAddr, name: &str, record: RecordType, wait: Duration) -> Result<Vec<Record>> {
+    let local = match resolver {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    query_from(local, resolver, name, record, wait).await
+}
+
+/// Like `query`, but sent from `local`, which must be of the resolver's
+/// address family.
+pub async fn query_from(
+    local: IpAddr,
+    resolver: SocketAddr,
+    name: &str,
+    record: RecordType,
+    wait: Duration,
+) -> Result<Vec<Record>> {
+    let socket = UdpSocket::bind((local, 0)).await.context("cannot open UDP socket")?;
+    socket.connect(resolver).await.context("cannot reach resolver")?;
+
+    let id = RandomState::new().build_hasher().finish() as u16;
+    let query = build_query(id, name, record)?;
+    socket.send(&query).await.context("cannot send query")?;
+
+    timeout(wait, async {
+        let mut buf = vec![0; MAX_RESPONSE_LEN];
+        loop {
+            let len = socket.recv(&mut buf).await.context("cannot read response")?;
+            // Stray or spoofed datagrams answer something else; keep waiting.
+            if answers_query(&buf[..len], &query) {
+                return parse_response(&buf[..len]);
+            }
+        }
+    })
+    .await
+    .map_err(|_| anyhow!("no answer from {}", resolver))?
+}
+
+pub fn build_query(id: u16, name: &str, record: RecordType) -> Result<Vec<u8>> {
+    let mut packet = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.extend_from_slice(&FLAG_RECURSION_DESIRED.to_be_bytes());
+    packet.extend_from_slice(&1u16.to_be_bytes());
+    packet.extend_from_slice(&[0; 6]);
+
+    let name = name.trim_end_matches('.');
+    if name.len() > 253 {
+        bail!("name '{}' is too long", name);
+    }
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            bail!("invalid name '{}'", name);
+        }
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+
+    packet.extend_from_slice(&record.code().to_be_bytes());
+    packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(packet)
+}
+
+/// Whether `response` carries the id of `query` and repeats its question.
+/// Names compare without regard to case, as resolvers may echo a query's
+/// letters in the case they were sent, or not.
+pub fn answers_query(response: &[u8], query: &[u8]) -> bool {
+    let question = &query[HEADER_LEN..];
+    response.len() >= query.len()
+        && response[..2] == query[..2]
+        && response[4..6] == 1u16.to_be_bytes()
+        && response[HEADER_LEN..query.len()].eq_ignore_ascii_case(question)
+}
+
+/// Extracts the A, AAAA, TXT and PTR answers from a response, turning error
+/// codes and truncation into errors.
+pub fn parse_response(packet: &[u8]) -> Result<Vec<Record>> {
+    let mut reader = Reader { packet, pos: 0 };
+    reader.take(2)?;
+    let flags = reader.u16()?;
+    let questions = reader.u16()?;
+    let answers = reader.u16()?;
+    reader.take(4)?;
+
+    if flags & FLAG_RESPONSE == 0 {
+        bail!("not a DNS response");
+    }
+    match flags & 0x000f {
+        0 => {}
+        1 => bail!("resolver rejected the query (FORMERR)"),
+        2 => bail!("resolver failed (SERVFAIL)"),
+        3 => bail!("no such name (NXDOMAIN)"),
+        5 => bail!("resolver refused the query (REFUSED)"),
+        code => bail!("resolver returned error code {}", code),
+    }
+    if flags & FLAG_TRUNCATED != 0 {
+        bail!("response truncated");
+    }
+
+    for _ in 0..questions {
+        reader.skip_name()?;
+        reader.take(4)?;
+    }
+
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        reader.skip_name()?;
+        let kind = reader.u16()?;
+        let class = reader.u16()?;
+        reader.take(4)?;
+        let len = reader.u16()? as usize;
+        let start = reader.pos;
+        let data = reader.take(len)?;
+
+        if class != CLASS_IN {
+            continue;
+        }
+        if kind == RecordType::A.code() {
+            let octets: [u8; 4] = data.try_into().map_err(|_| anyhow!("malformed A record"))?;
+            records.push(Record::Address(IpAddr::from(octets)));
+        } else if kind == RecordType::Aaaa.code() {
+            let octets: [u8; 16] = data.try_into().map_err(|_| anyhow!("malformed AAAA record"))?;
+            records.push(Record::Address(IpAddr::from(octets)));
+        } else if kind == RecordType::Txt.code() {
+            records.push(Record::Txt(parse_txt(data)?));
+        } else if kind == RecordType::Ptr.code() {
+            records.push(Record::Name(Reader { packet, pos: start }.read_name()?));
+        }
+    }
+    Ok(records)
+}
+
+/// Joins the character-strings of a TXT record.
+fn parse_txt(data: &[u8]) -> Result<String> {
+    let mut reader = Reader { packet: data, pos: 0 };
+    let mut text = String::new();
+    while reader.pos < data.len() {
+        let len = reader.take(1)?[0] as usize;
+        text.push_str(&String::from_utf8_lossy(reader.take(len)?));
+    }
+    Ok(text)
+}
+
+struct Reader<'a> {
+    packet: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .packet
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| anyhow!("response ends early"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Reads a possibly compressed name, following pointers anywhere earlier
+    /// in the packet.
+    fn read_name(&mut self) -> Result<String> {
+        let mut labels: Vec<String> = Vec::new();
+        let mut reader = Reader { packet: self.packet, pos: self.pos };
+        let mut jumps = 0;
+        loop {
+            let len = reader.take(1)?[0];
+            match len & 0xc0 {
+                0xc0 => {
+                    let target = usize::from(len & 0x3f) << 8 | usize::from(reader.take(1)?[0]);
+                    if jumps == 0 {
+                        self.pos = reader.pos;
+                    }
+                    jumps += 1;
+                    if jumps > MAX_POINTER_JUMPS {
+                        bail!("name compression loop");
+                    }
+                    reader.pos = target;
+                }
+                0x00 if len == 0 => {
+                    if jumps == 0 {
+                        self.pos = reader.pos;
+                    }
+                    return Ok(labels.join("."));
+                }
+                0x00 => labels.push(String::from_utf8_lossy(reader.take(len as usize)?).into_owned()),
+                _ => bail!("malformed name"),
+            }
+        }
+    }
+
+    /// Steps over a possibly compressed name; a compression pointer always
+    /// ends the name in place.
+    fn skip_name(&mut self) -> Result<()> {
+        loop {
+            let len = self.take(1)?[0];
+            match len & 0xc0 {
+                0xc0 => {
+                    self.take(1)?;
+                    return Ok(());
+                }
+                0x00 if len == 0 => return Ok(()),
+                0x00 => {
+                    self.take(len as usize)?;
+                }
+                _ => bail!("malformed name"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUERY_ID: u16 = 0x1234;
+
+    /// A response to `query` with `rcode`, whose answers all name the question
+    /// through a compression pointer.
+    fn response(query: &[u8], rcode: u16, answers: &[(RecordType, &[u8])]) -> Vec<u8> {
+        let mut packet = query[..2].to_vec();
+        packet.extend_from_slice(&(FLAG_RESPONSE | FLAG_RECURSION_DESIRED | 0x0080 | rcode).to_be_bytes());
+        packet.extend_from_slice(&1u16.to_be_bytes());
+        packet.extend_from_slice(&(answers.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&[0; 4]);
+        packet.extend_from_slice(&query[HEADER_LEN..]);
+        for (record, data) in answers {
+            packet.extend_from_slice(&[0xc0, HEADER_LEN as u8]);
+            packet.extend_from_slice(&record.code().to_be_bytes());
+            packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+            packet.extend_from_slice(&300u32.to_be_bytes());
+            packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            packet.extend_from_slice(data);
+        }
+        packet
+    }
+
+    fn query_packet(name: &str, record: RecordType) -> Vec<u8> {
+        build_query(QUERY_ID, name, record).unwrap()
+    }
+
+    #[test]
+    fn query_bytes() {
+        let expected = [
+            0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0,
+            0x00, 0x1c, 0x00, 0x01,
+        ];
+
+        assert_eq!(query_packet("example.com.", RecordType::Aaaa), expected);
+        assert_eq!(query_packet("example.com", RecordType::Aaaa), expected);
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        assert!(build_query(QUERY_ID, "a..example", RecordType::A).is_err());
+        assert!(build_query(QUERY_ID, "", RecordType::A).is_err());
+        assert!(build_query(QUERY_ID, &"x".repeat(64), RecordType::A).is_err());
+        assert!(build_query(QUERY_ID, &vec!["x".repeat(63); 4].join("."), RecordType::A).is_err());
+    }
+
+    #[test]
+    fn address_and_txt_answers() {
+        let query = query_packet("myip.example", RecordType::Txt);
+        let packet = response(&query, 0, &[
+            (RecordType::A, &[203, 0, 113, 7]),
+            (RecordType::Txt, b"\x0b203.0.113.7\x04 ok!"),
+        ]);
+
+        assert_eq!(parse_response(&packet).unwrap(), [
+            Record::Address("203.0.113.7".parse().unwrap()),
+            Record::Txt("203.0.113.7 ok!".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn compression_pointers_in_names() {
+        let query = query_packet("7.113.0.203.in-addr.arpa", RecordType::Ptr);
+        // "host" followed by a pointer to "in-addr.arpa" inside the question.
+        let suffix = HEADER_LEN as u8 + 12;
+        let packet = response(&query, 0, &[(RecordType::Ptr, &[4, b'h', b'o', b's', b't', 0xc0, suffix])]);
+
+        assert_eq!(parse_response(&packet).unwrap(), [Record::Name("host.in-addr.arpa".to_string())]);
+    }
+
+    #[test]
+    fn pointer_loop_hits_the_jump_limit() {
+        let query = query_packet("7.113.0.203.in-addr.arpa", RecordType::Ptr);
+        let mut packet = response(&query, 0, &[(RecordType::Ptr, &[0xc0, 0])]);
+        // Point the record's data at itself.
+        let data = packet.len() - 2;
+        packet[data + 1] = data as u8;
+
+        let error = parse_response(&packet).unwrap_err();
+        assert_eq!(error.to_string(), "name compression loop");
+    }
+
+    #[test]
+    fn truncated_responses() {
+        let query = query_packet("example.com", RecordType::A);
+        let mut packet = response(&query, 0, &[(RecordType::A, &[192, 0, 2, 1])]);
+
+        for len in [5, HEADER_LEN + 3, packet.len() - 1] {
+            assert_eq!(parse_response(&packet[..len]).unwrap_err().to_string(), "response ends early");
+        }
+        packet[2] |= (FLAG_TRUNCATED >> 8) as u8;
+        assert_eq!(parse_response(&packet).unwrap_err().to_string(), "response truncated");
+    }
+
+    #[test]
+    fn error_codes() {
+        let query = query_packet("missing.example", RecordType::A);
+
+        assert_eq!(parse_response(&response(&query, 3, &[])).unwrap_err().to_string(), "no such name (NXDOMAIN)");
+        assert_eq!(parse_response(&response(&query, 2, &[])).unwrap_err().to_string(), "resolver failed (SERVFAIL)");
+        assert_eq!(parse_response(&query).unwrap_err().to_string(), "not a DNS response");
+    }
+
+    #[test]
+    fn malformed_address_record() {
+        let query = query_packet("example.com", RecordType::A);
+        let packet = response(&query, 0, &[(RecordType::A, &[192, 0, 2])]);
+
+        assert_eq!(parse_response(&packet).unwrap_err().to_string(), "malformed A record");
+    }
+
+    #[test]
+    fn responses_must_match_the_query() {
+        let query = query_packet("Example.com", RecordType::A);
+        let packet = response(&query, 0, &[]);
+        assert!(answers_query(&packet, &query));
+
+        let mut other_id = packet.clone();
+        other_id[1] ^= 0xff;
+        assert!(!answers_query(&other_id, &query));
+
+        let mut other_case = packet.clone();
+        other_case[HEADER_LEN + 1] = b'e';
+        assert!(answers_query(&other_case, &query));
+
+        let other_name = response(&build_query(QUERY_ID, "example.org", RecordType::A).unwrap(), 0, &[]);
+        assert!(!answers_query(&other_name, &query));
+        let other_type = response(&build_query(QUERY_ID, "example.com", RecordType::Aaaa).unwrap(), 0, &[]);
+        assert!(!answers_query(&other_type, &query));
+        assert!(!answers_query(&packet[..HEADER_LEN], &query));
+    }
+
+    #[test]
+    fn reverse_names() {
+        assert_eq!(reverse_name("192.0.2.1".parse().unwrap()), "1.2.0.192.in-addr.arpa");
+        assert_eq!(
+            reverse_name("2001:db8::1".parse().unwrap()),
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+    }
+
+    #[tokio::test]
+    async fn query_from_a_stub_resolver() {
+        let resolver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = resolver.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            let (len, client) = resolver.recv_from(&mut buf).await.unwrap();
+            let query = &buf[..len];
+
+            let mut stray = response(query, 0, &[(RecordType::A, &[198, 51, 100, 1])]);
+            stray[0] ^= 0xff;
+            let mut other_question = query.to_vec();
+            *other_question.last_mut().unwrap() = 3;
+            let other_question = response(&other_question, 0, &[(RecordType::A, &[198, 51, 100, 2])]);
+            let answer = response(query, 0, &[(RecordType::A, &[203, 0, 113, 7])]);
+            for packet in [stray, other_question, answer] {
+                resolver.send_to(&packet, client).await.unwrap();
+            }
+        });
+
+        let records = query_from(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            address,
+            "myip.example",
+            RecordType::A,
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(records, [Record::Address("203.0.113.7".parse().unwrap())]);
+    }
+
+    #[tokio::test]
+    async fn silent_resolver_times_out() {
+        let resolver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = resolver.local_addr().unwrap();
+
+        let error = query(address, "myip.example", RecordType::A, Duration::from_millis(50)).await.unwrap_err();
+
+        assert_eq!(error.to_string(), format!("no answer from {}", address));
+    }
+
+    #[test]
+    fn system_resolver_skips_the_resolved_stub() {
+        let root = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/resolver"));
+
+        assert_eq!(resolver_below(root), "192.0.2.53:53".parse().unwrap());
+    }
+
+    #[test]
+    fn system_resolver_defaults_to_the_local_host() {
+        assert_eq!(resolver_below(Path::new("/nonexistent")), "127.0.0.1:53".parse().unwrap());
+    }
+}
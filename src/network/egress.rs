@@ -0,0 +1,341 @@
+use super::{host_interfaces, AddressScope, Interface, IpFamily};
+use crate::config::Config;
+use anyhow::{bail, Context, Result};
+use reqwest::{Certificate, Proxy, Url};
+use std::env;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Documentation addresses, which no network has a route for, so the route
+/// to them is the one Internet traffic takes.
+const ROUTE_CHECK_V4: IpAddr = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1));
+const ROUTE_CHECK_V6: IpAddr = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+
+/// How public IP lookups leave the host: the proxy HTTP providers go through,
+/// the extra root certificates trusted for HTTPS, and the interface whose
+/// address requests are sent from. The default connects directly, ignoring
+/// the environment.
+#[derive(Debug, Clone, Default)]
+pub struct Egress {
+    proxies: Arc<Proxies>,
+    certificates: Vec<Certificate>,
+    bind_interface: Option<String>,
+}
+
+/// Proxy settings read once at startup. The configured proxy replaces the
+/// `HTTPS_PROXY`/`HTTP_PROXY` environment variables, while `NO_PROXY` applies
+/// to either.
+#[derive(Debug, Default)]
+struct Proxies {
+    configured: Option<Url>,
+    https: Option<Url>,
+    http: Option<Url>,
+    no_proxy: Vec<String>,
+}
+
+impl Egress {
+    /// Fails if a CA bundle cannot be read or holds no certificates.
+    pub fn new(config: &Config) -> Result<Self> {
+        let certificates = config
+            .ca_certs
+            .iter()
+            .map(|path| read_certificates(path))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        let proxies = Proxies {
+            configured: config.proxy.clone(),
+            https: env_proxy(&["https_proxy", "HTTPS_PROXY", "all_proxy", "ALL_PROXY"]),
+            http: env_proxy(&["http_proxy", "HTTP_PROXY", "all_proxy", "ALL_PROXY"]),
+            no_proxy: env_var(&["no_proxy", "NO_PROXY"])
+                .map(|value| value.split(',').map(|entry| entry.trim().to_lowercase()).collect())
+                .unwrap_or_default(),
+        };
+
+        Ok(Egress {
+            proxies: Arc::new(proxies),
+            certificates,
+            bind_interface: config.bind_interface.clone(),
+        })
+    }
+
+    /// The same settings with lookups sent from `interface` instead.
+    pub fn bound_to(&self, interface: &str) -> Egress {
+        Egress {
+            bind_interface: Some(interface.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// The proxy a request to `url` goes through, credentials included, or
+    /// `None` to connect directly.
+    fn proxy_for(&self, url: &Url) -> Option<Url> {
+        self.proxies.proxy_for(url)
+    }
+
+    /// The proxy a request to `url` goes through, without credentials, for
+    /// showing alongside its answer.
+    pub fn proxy_label(&self, url: &str) -> Option<String> {
+        let proxy = self.proxy_for(&Url::parse(url).ok()?)?;
+        Some(redact(&proxy))
+    }
+
+    /// The address lookups of `family` are sent from: the unspecified
+    /// address, or a usable address of the bound interface so the answer is
+    /// that interface's egress address. Requests only carry the source
+    /// address, so on Linux one is only picked if the kernel routes traffic
+    /// from it out through the interface itself. Picking one reads procfs and
+    /// asks the kernel for a route, so it runs on the blocking pool.
+    pub async fn local_address(&self, family: IpFamily) -> Result<IpAddr, String> {
+        let Some(name) = self.bind_interface.clone() else {
+            return Ok(match family {
+                IpFamily::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                IpFamily::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            });
+        };
+        tokio::task::spawn_blocking(move || interface_address(&name, family))
+            .await
+            .unwrap_or_else(|error| Err(format!("cannot pick a source address: {}", error)))
+    }
+
+    /// An HTTP client whose connections leave from `local_address`, go
+    /// through the proxy chosen for each URL and trust the extra CAs.
+    pub fn client(&self, local_address: IpAddr, timeout: Duration) -> reqwest::Result<reqwest::Client> {
+        let proxies = self.proxies.clone();
+        let mut builder = reqwest::Client::builder()
+            .timeout(timeout)
+            .local_address(local_address)
+            .proxy(Proxy::custom(move |url| proxies.proxy_for(url)));
+        for certificate in &self.certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        builder.build()
+    }
+}
+
+/// Lists the interfaces and picks the address of `family` on `name` that
+/// lookups bound to it are sent from.
+fn interface_address(name: &str, family: IpFamily) -> Result<IpAddr, String> {
+    let interfaces = host_interfaces().map_err(|error| format!("cannot list interfaces: {}", error))?;
+    pick_source(&interfaces, name, family, output_interface)
+}
+
+/// The first settled, non-link-local address of `family` on the interface
+/// `name` whose traffic `route` does not place on another interface.
+/// Addresses that are still tentative or deprecated are not used for new
+/// connections.
+fn pick_source(
+    interfaces: &[Interface],
+    name: &str,
+    family: IpFamily,
+    route: impl Fn(IpAddr, IpAddr) -> Option<u32>,
+) -> Result<IpAddr, String> {
+    let interface = interfaces
+        .iter()
+        .find(|interface| interface.name == name)
+        .ok_or_else(|| format!("no interface named {}", name))?;
+    let addresses = match family {
+        IpFamily::V4 => &interface.ipv4_addresses,
+        IpFamily::V6 => &interface.ipv6_addresses,
+    };
+    let mut candidates = addresses
+        .iter()
+        .filter(|address| !matches!(address.scope, AddressScope::LinkLocal | AddressScope::Loopback))
+        .filter(|address| !address.flags.tentative && !address.flags.deprecated)
+        .map(|address| address.address)
+        .peekable();
+    if candidates.peek().is_none() {
+        return Err(format!("interface {} has no usable {} address", interface.name, family.label()));
+    }
+
+    let destination = match family {
+        IpFamily::V4 => ROUTE_CHECK_V4,
+        IpFamily::V6 => ROUTE_CHECK_V6,
+    };
+    let mut elsewhere = None;
+    for source in candidates {
+        match route(destination, source) {
+            Some(index) if index != interface.index => elsewhere = elsewhere.or(Some((source, index))),
+            _ => return Ok(source),
+        }
+    }
+    let (source, index) = elsewhere.expect("at least one candidate");
+    let through = interfaces
+        .iter()
+        .find(|other| other.index == index)
+        .map_or_else(|| format!("link {}", index), |other| other.name.clone());
+    Err(format!(
+        "traffic from {} leaves through {}, not {}; add a source routing rule for it",
+        source, through, name
+    ))
+}
+
+/// The interface the kernel sends traffic from `source` to `destination`
+/// through, or `None` when that cannot be told.
+#[cfg(target_os = "linux")]
+fn output_interface(destination: IpAddr, source: IpAddr) -> Option<u32> {
+    super::netlink::output_interface(destination, source).ok().flatten()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn output_interface(_destination: IpAddr, _source: IpAddr) -> Option<u32> {
+    None
+}
+
+impl Proxies {
+    fn proxy_for(&self, url: &Url) -> Option<Url> {
+        let host = url.host_str()?.trim_start_matches('[').trim_end_matches(']');
+        if self.no_proxy.iter().any(|entry| bypasses(entry, host)) {
+            return None;
+        }
+        if let Some(proxy) = &self.configured {
+            return Some(proxy.clone());
+        }
+        match url.scheme() {
+            "https" => self.https.clone(),
+            "http" => self.http.clone(),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a proxy URL as given in the config file, on the command line or in
+/// the environment, taking a bare `host:port` as an HTTP proxy.
+pub fn parse_proxy(value: &str) -> Result<Url, String> {
+    let value = value.trim();
+    let url = if value.contains("://") {
+        Url::parse(value)
+    } else {
+        Url::parse(&format!("http://{}", value))
+    }
+    .map_err(|error| format!("invalid proxy URL '{}': {}", value, error))?;
+
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(format!("proxy '{}' must be an http:// or https:// URL", redact(&url)));
+    }
+    if url.host_str().is_none() {
+        return Err(format!("proxy '{}' has no host", value));
+    }
+    Ok(url)
+}
+
+/// `scheme://host[:port]` of a proxy, leaving out any user name and password.
+fn redact(proxy: &Url) -> String {
+    let host = proxy.host_str().unwrap_or_default();
+    match proxy.port() {
+        Some(port) => format!("{}://{}:{}", proxy.scheme(), host, port),
+        None => format!("{}://{}", proxy.scheme(), host),
+    }
+}
+
+/// Whether a `NO_PROXY` entry covers `host`: `*` covers everything, and a
+/// name covers itself and its subdomains, with or without a leading dot.
+fn bypasses(entry: &str, host: &str) -> bool {
+    let entry = entry.trim_start_matches("*.").trim_start_matches('.');
+    if entry.is_empty() {
+        return false;
+    }
+    let host = host.to_lowercase();
+    entry == "*" || host == entry || host.strip_suffix(entry).is_some_and(|rest| rest.ends_with('.'))
+}
+
+fn env_var(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.trim().is_empty()))
+}
+
+/// The first proxy set in the environment. An unparsable value is ignored
+/// like curl and reqwest do, rather than failing every lookup.
+fn env_proxy(names: &[&str]) -> Option<Url> {
+    parse_proxy(&env_var(names)?).ok()
+}
+
+fn read_certificates(path: &Path) -> Result<Vec<Certificate>> {
+    let pem = fs::read(path).with_context(|| format!("Failed to read CA bundle {}", path.display()))?;
+    let certificates = Certificate::from_pem_bundle(&pem)
+        .with_context(|| format!("Invalid CA bundle {}", path.display()))?;
+    if certificates.is_empty() {
+        bail!("CA bundle {} holds no PEM certificates", path.display());
+    }
+    Ok(certificates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{AddressFlags, InterfaceAddress};
+
+    fn address(address: &str, flags: AddressFlags) -> InterfaceAddress {
+        let address: IpAddr = address.parse().unwrap();
+        InterfaceAddress {
+            address,
+            prefix_len: 64,
+            scope: AddressScope::of(&address),
+            flags,
+        }
+    }
+
+    fn uplinks() -> Vec<Interface> {
+        let mut eth0 = Interface::new("eth0", 2);
+        eth0.ipv4_addresses.push(address("192.0.2.10", AddressFlags::default()));
+        eth0.ipv6_addresses = vec![
+            address("fe80::1", AddressFlags::default()),
+            address("2001:db8::10", AddressFlags { tentative: true, ..AddressFlags::default() }),
+            address("2001:db8::20", AddressFlags { deprecated: true, ..AddressFlags::default() }),
+            address("2001:db8::30", AddressFlags { temporary: true, ..AddressFlags::default() }),
+        ];
+        let mut wwan0 = Interface::new("wwan0", 3);
+        wwan0.ipv4_addresses.push(address("100.64.0.7", AddressFlags::default()));
+        wwan0.ipv6_addresses.push(address("fe80::2", AddressFlags::default()));
+        vec![eth0, wwan0]
+    }
+
+    /// Routes everything through eth0, like a host without source rules.
+    fn default_route(_destination: IpAddr, _source: IpAddr) -> Option<u32> {
+        Some(2)
+    }
+
+    #[test]
+    fn settled_addresses_are_picked() {
+        let interfaces = uplinks();
+
+        assert_eq!(pick_source(&interfaces, "eth0", IpFamily::V4, default_route), Ok("192.0.2.10".parse().unwrap()));
+        assert_eq!(pick_source(&interfaces, "eth0", IpFamily::V6, default_route), Ok("2001:db8::30".parse().unwrap()));
+    }
+
+    #[test]
+    fn missing_interfaces_and_addresses() {
+        let interfaces = uplinks();
+
+        assert_eq!(pick_source(&interfaces, "wlan0", IpFamily::V4, default_route), Err("no interface named wlan0".to_string()));
+        assert_eq!(
+            pick_source(&interfaces, "wwan0", IpFamily::V6, default_route),
+            Err("interface wwan0 has no usable IPv6 address".to_string())
+        );
+    }
+
+    #[test]
+    fn sources_routed_elsewhere_are_refused() {
+        let interfaces = uplinks();
+
+        assert_eq!(
+            pick_source(&interfaces, "wwan0", IpFamily::V4, default_route),
+            Err("traffic from 100.64.0.7 leaves through eth0, not wwan0; add a source routing rule for it".to_string())
+        );
+        let source_rules = |_, source: IpAddr| Some(if source.to_string() == "100.64.0.7" { 3 } else { 2 });
+        assert_eq!(pick_source(&interfaces, "wwan0", IpFamily::V4, source_rules), Ok("100.64.0.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn unknown_routes_keep_the_source() {
+        let interfaces = uplinks();
+
+        assert_eq!(pick_source(&interfaces, "wwan0", IpFamily::V4, |_, _| None), Ok("100.64.0.7".parse().unwrap()));
+    }
+}
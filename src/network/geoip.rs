@@ -0,0 +1,179 @@
+use anyhow::{Context, Result};
+use maxminddb::{geoip2, MaxMindDBError, Reader};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+/// Paths of the local MaxMind-format databases to enrich public addresses
+/// with; either may be left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeoIpPaths {
+    /// A GeoIP2/GeoLite2 City or Country database.
+    pub city: Option<PathBuf>,
+    /// A GeoLite2 ASN database.
+    pub asn: Option<PathBuf>,
+}
+
+/// Where an address is registered and who announces it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GeoInfo {
+    pub country: Option<String>,
+    pub country_code: Option<String>,
+    pub city: Option<String>,
+    pub asn: Option<u32>,
+    pub organization: Option<String>,
+}
+
+/// Open databases, read fully into memory once at startup so lookups never
+/// touch the disk or the network.
+pub struct GeoIp {
+    city: Option<Reader<Vec<u8>>>,
+    asn: Option<Reader<Vec<u8>>>,
+}
+
+impl std::fmt::Debug for GeoIp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GeoIp")
+            .field("city", &self.city.is_some())
+            .field("asn", &self.asn.is_some())
+            .finish()
+    }
+}
+
+impl GeoIp {
+    /// Opens the configured databases, or returns `None` if there are none.
+    pub fn open(paths: &GeoIpPaths) -> Result<Option<Self>> {
+        if paths.city.is_none() && paths.asn.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(GeoIp {
+            city: paths.city.as_ref().map(open_database).transpose()?,
+            asn: paths.asn.as_ref().map(open_database).transpose()?,
+        }))
+    }
+
+    /// Looks `address` up in every open database; `None` if none knows it.
+    pub fn lookup(&self, address: IpAddr) -> Option<GeoInfo> {
+        let mut info = GeoInfo::default();
+
+        if let Some(city) = self.city.as_ref().and_then(|reader| found(reader.lookup::<geoip2::City>(address))) {
+            let country = city.country.or(city.registered_country);
+            info.country = country.as_ref().and_then(|country| english(&country.names));
+            info.country_code = country.and_then(|country| country.iso_code).map(str::to_string);
+            info.city = city.city.and_then(|city| english(&city.names));
+        }
+        if let Some(asn) = self.asn.as_ref().and_then(|reader| found(reader.lookup::<geoip2::Asn>(address))) {
+            info.asn = asn.autonomous_system_number;
+            info.organization = asn.autonomous_system_organization.map(str::to_string);
+        }
+
+        (info != GeoInfo::default()).then_some(info)
+    }
+}
+
+fn open_database(path: &PathBuf) -> Result<Reader<Vec<u8>>> {
+    Reader::open_readfile(path).with_context(|| format!("Failed to open GeoIP database {}", path.display()))
+}
+
+/// Treats any lookup error as "not found": a database that opened cleanly but
+/// cannot decode one record should not take the panel down with it.
+fn found<T>(result: Result<T, MaxMindDBError>) -> Option<T> {
+    result.ok()
+}
+
+fn english(names: &Option<BTreeMap<&str, &str>>) -> Option<String> {
+    names.as_ref()?.get("en").map(|name| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> Option<PathBuf> {
+        Some(PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/geoip")).join(name))
+    }
+
+    fn both() -> GeoIp {
+        GeoIp::open(&GeoIpPaths {
+            city: fixture("city.mmdb"),
+            asn: fixture("asn.mmdb"),
+        })
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn city_and_asn() {
+        assert_eq!(both().lookup("198.51.100.7".parse().unwrap()), Some(GeoInfo {
+            country: Some("Netherlands".to_string()),
+            country_code: Some("NL".to_string()),
+            city: Some("Amsterdam".to_string()),
+            asn: Some(64496),
+            organization: Some("Example Transit".to_string()),
+        }));
+    }
+
+    #[test]
+    fn registered_country_stands_in_for_a_missing_country() {
+        assert_eq!(both().lookup("203.0.113.7".parse().unwrap()), Some(GeoInfo {
+            country: Some("United States".to_string()),
+            country_code: Some("US".to_string()),
+            ..GeoInfo::default()
+        }));
+    }
+
+    #[test]
+    fn asn_only_database() {
+        let geoip = GeoIp::open(&GeoIpPaths {
+            city: None,
+            asn: fixture("asn.mmdb"),
+        })
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(geoip.lookup("198.51.100.7".parse().unwrap()), Some(GeoInfo {
+            asn: Some(64496),
+            organization: Some("Example Transit".to_string()),
+            ..GeoInfo::default()
+        }));
+        assert_eq!(geoip.lookup("192.0.2.1".parse().unwrap()), Some(GeoInfo {
+            asn: Some(64511),
+            ..GeoInfo::default()
+        }));
+        assert_eq!(geoip.lookup("203.0.113.7".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn unknown_addresses() {
+        let geoip = both();
+
+        assert_eq!(geoip.lookup("8.8.8.8".parse().unwrap()), None);
+        // The fixtures are IPv4-only, so every IPv6 lookup fails.
+        assert_eq!(geoip.lookup("2001:db8::1".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn no_databases() {
+        assert!(GeoIp::open(&GeoIpPaths::default()).unwrap().is_none());
+    }
+
+    #[test]
+    fn unreadable_database() {
+        let error = GeoIp::open(&GeoIpPaths {
+            city: fixture("missing.mmdb"),
+            asn: None,
+        })
+        .unwrap_err();
+        assert!(format!("{:#}", error).starts_with("Failed to open GeoIP database /"));
+        assert!(error.to_string().ends_with("tests/fixtures/geoip/missing.mmdb"));
+
+        let error = GeoIp::open(&GeoIpPaths {
+            city: None,
+            asn: fixture("generate.py"),
+        })
+        .unwrap_err();
+        assert!(error.to_string().ends_with("tests/fixtures/geoip/generate.py"));
+    }
+}
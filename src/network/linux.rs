@@ -0,0 +1,546 @@
+use super::routes::{parse_rt_tables, Route};
+use super::sockets::{Connection, Protocol};
+use super::{AddressFlags, IpFamily};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+const IFA_F_TEMPORARY: u32 = 0x01;
+const IFA_F_DEPRECATED: u32 = 0x20;
+const IFA_F_TENTATIVE: u32 = 0x40;
+
+const RTF_UP: u32 = 0x0001;
+const RTF_GATEWAY: u32 = 0x0002;
+const RTF_REJECT: u32 = 0x0200;
+const RTF_CACHE: u32 = 0x0100_0000;
+const RTF_LOCAL: u32 = 0x8000_0000;
+
+/// `TCP_*` states in the order of their `/proc/net/tcp` codes, named as `ss`
+/// shows them.
+const TCP_STATES: [&str; 11] = [
+    "ESTAB", "SYN-SENT", "SYN-RECV", "FIN-WAIT-1", "FIN-WAIT-2", "TIME-WAIT",
+    "UNCONN", "CLOSE-WAIT", "LAST-ACK", "LISTEN", "CLOSING",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LinkCounters {
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub rx_errors: u64,
+    pub rx_dropped: u64,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+    pub tx_errors: u64,
+    pub tx_dropped: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkDetails {
+    pub mac_address: Option<String>,
+    pub mtu: Option<u32>,
+    pub speed: Option<u64>,
+    pub status: bool,
+    /// Kernel module bound to the device; virtual interfaces have none.
+    pub driver: Option<String>,
+}
+
+/// Reads interface state from procfs and sysfs below `root`, which is `/` on
+/// a live system and a fixture directory in tests.
+#[derive(Debug, Clone)]
+pub struct LinuxCollector {
+    root: PathBuf,
+}
+
+impl LinuxCollector {
+    pub fn new() -> Self {
+        Self::with_root("/")
+    }
+
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        LinuxCollector { root: root.into() }
+    }
+
+    pub fn counters(&self) -> Result<HashMap<String, LinkCounters>> {
+        let path = self.root.join("proc/net/dev");
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(parse_proc_net_dev(&content))
+    }
+
+    pub fn link_details(&self, name: &str) -> LinkDetails {
+        let dir = self.root.join("sys/class/net").join(name);
+        let read = |attr: &str| {
+            fs::read_to_string(dir.join(attr))
+                .ok()
+                .map(|value| value.trim().to_string())
+        };
+
+        let mac_address = read("address")
+            .filter(|mac| !mac.is_empty() && mac != "00:00:00:00:00:00");
+        let mtu = read("mtu").and_then(|mtu| mtu.parse().ok());
+        let speed = read("speed")
+            .and_then(|speed| speed.parse::<i64>().ok())
+            .filter(|speed| *speed > 0)
+            .map(|speed| speed as u64);
+        let carrier = read("carrier").as_deref() == Some("1");
+        let status = match read("operstate").as_deref() {
+            Some("up") => true,
+            Some("unknown") => carrier,
+            _ => false,
+        };
+
+        let driver = fs::read_link(dir.join("device/driver"))
+            .ok()
+            .and_then(|target| Some(target.file_name()?.to_string_lossy().into_owned()));
+
+        LinkDetails {
+            mac_address,
+            mtu,
+            speed,
+            status,
+            driver,
+        }
+    }
+
+    /// The main routing table of both families, skipping the kernel's local
+    /// and cached IPv6 entries that `ip -6 route` does not show either. Only
+    /// used where rtnetlink cannot be dumped, since procfs has no other table.
+    pub fn routes(&self) -> Result<Vec<Route>> {
+        let path = self.root.join("proc/net/route");
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut routes = parse_proc_net_route(&content);
+        // A kernel without IPv6 has no ipv6_route at all.
+        if let Ok(content) = fs::read_to_string(self.root.join("proc/net/ipv6_route")) {
+            routes.extend(parse_ipv6_route(&content));
+        }
+        Ok(routes)
+    }
+
+    /// Every TCP and UDP socket of both families in this network namespace.
+    pub fn connections(&self) -> Result<Vec<Connection>> {
+        let mut connections = Vec::new();
+        for (file, protocol) in [
+            ("proc/net/tcp", Protocol::Tcp),
+            ("proc/net/tcp6", Protocol::Tcp),
+            ("proc/net/udp", Protocol::Udp),
+            ("proc/net/udp6", Protocol::Udp),
+        ] {
+            if let Ok(content) = fs::read_to_string(self.root.join(file)) {
+                connections.extend(parse_proc_net_sockets(&content, protocol));
+            }
+        }
+        Ok(connections)
+    }
+
+    /// Routing table names from iproute2's `rt_tables`, the distribution's
+    /// defaults first so local additions override them.
+    pub fn table_names(&self) -> HashMap<u32, String> {
+        let mut names = HashMap::new();
+        for dir in ["usr/share/iproute2", "usr/lib/iproute2", "etc/iproute2"] {
+            let dir = self.root.join(dir);
+            let mut files = vec![dir.join("rt_tables")];
+            if let Ok(entries) = fs::read_dir(dir.join("rt_tables.d")) {
+                let mut extra: Vec<PathBuf> = entries
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .filter(|path| path.extension().is_some_and(|extension| extension == "conf"))
+                    .collect();
+                extra.sort();
+                files.extend(extra);
+            }
+            for file in files {
+                if let Ok(content) = fs::read_to_string(file) {
+                    names.extend(parse_rt_tables(&content));
+                }
+            }
+        }
+        names
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn ipv6_flags(&self) -> HashMap<(String, Ipv6Addr), AddressFlags> {
+        fs::read_to_string(self.root.join("proc/net/if_inet6"))
+            .map(|content| parse_if_inet6(&content))
+            .unwrap_or_default()
+    }
+}
+
+/// Parses `/proc/net/dev`. Interface names may contain colons, the counters
+/// never do, so the name ends at the last one.
+pub fn parse_proc_net_dev(content: &str) -> HashMap<String, LinkCounters> {
+    let mut counters = HashMap::new();
+
+    for line in content.lines() {
+        let Some((name, stats)) = line.rsplit_once(':') else {
+            continue;
+        };
+        let fields: Vec<u64> = stats
+            .split_whitespace()
+            .map(|field| field.parse().unwrap_or(0))
+            .collect();
+        if fields.len() < 16 {
+            continue;
+        }
+        counters.insert(name.trim().to_string(), LinkCounters {
+            rx_bytes: fields[0],
+            rx_packets: fields[1],
+            rx_errors: fields[2],
+            rx_dropped: fields[3],
+            tx_bytes: fields[8],
+            tx_packets: fields[9],
+            tx_errors: fields[10],
+            tx_dropped: fields[11],
+        });
+    }
+    counters
+}
+
+pub fn parse_if_inet6(content: &str) -> HashMap<(String, Ipv6Addr), AddressFlags> {
+    let mut flags = HashMap::new();
+
+    for line in content.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 6 {
+            continue;
+        }
+        let (Ok(addr), Ok(raw)) = (u128::from_str_radix(parts[0], 16), u32::from_str_radix(parts[4], 16)) else {
+            continue;
+        };
+        flags.insert((parts[5].to_string(), Ipv6Addr::from(addr)), AddressFlags {
+            temporary: raw & IFA_F_TEMPORARY != 0,
+            deprecated: raw & IFA_F_DEPRECATED != 0,
+            tentative: raw & IFA_F_TENTATIVE != 0,
+        });
+    }
+    flags
+}
+
+/// Parses `/proc/net/route`, whose addresses are hex dumps of the kernel's
+/// network-order words.
+pub fn parse_proc_net_route(content: &str) -> Vec<Route> {
+    let mut routes = Vec::new();
+
+    for line in content.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 8 {
+            continue;
+        }
+        let hex = |field: &str| u32::from_str_radix(field, 16).ok();
+        let (Some(destination), Some(gateway), Some(flags), Ok(metric), Some(mask)) =
+            (hex(fields[1]), hex(fields[2]), hex(fields[3]), fields[6].parse(), hex(fields[7]))
+        else {
+            continue;
+        };
+        if flags & RTF_UP == 0 {
+            continue;
+        }
+        routes.push(Route {
+            family: IpFamily::V4,
+            destination: IpAddr::V4(Ipv4Addr::from(destination.to_ne_bytes())),
+            prefix_len: mask.count_ones() as u8,
+            gateway: (flags & RTF_GATEWAY != 0).then(|| IpAddr::V4(Ipv4Addr::from(gateway.to_ne_bytes()))),
+            interface: Some(fields[0].to_string()),
+            metric,
+            table: "main".to_string(),
+            kind: route_kind(flags),
+            source: None,
+        });
+    }
+    routes
+}
+
+pub fn parse_ipv6_route(content: &str) -> Vec<Route> {
+    let mut routes = Vec::new();
+
+    for line in content.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 10 {
+            continue;
+        }
+        let (Ok(destination), Ok(prefix_len), Ok(gateway), Ok(metric), Ok(flags)) = (
+            u128::from_str_radix(fields[0], 16),
+            u8::from_str_radix(fields[1], 16),
+            u128::from_str_radix(fields[4], 16),
+            u32::from_str_radix(fields[5], 16),
+            u32::from_str_radix(fields[8], 16),
+        ) else {
+            continue;
+        };
+        if flags & RTF_UP == 0 || flags & (RTF_CACHE | RTF_LOCAL) != 0 {
+            continue;
+        }
+        // The table's catch-all entry on lo that rejects unrouted traffic.
+        if flags & RTF_REJECT != 0 && prefix_len == 0 && fields[9] == "lo" {
+            continue;
+        }
+        routes.push(Route {
+            family: IpFamily::V6,
+            destination: IpAddr::V6(Ipv6Addr::from(destination)),
+            prefix_len,
+            gateway: (flags & RTF_GATEWAY != 0).then(|| IpAddr::V6(Ipv6Addr::from(gateway))),
+            interface: Some(fields[9].to_string()),
+            metric,
+            table: "main".to_string(),
+            kind: route_kind(flags),
+            source: None,
+        });
+    }
+    routes
+}
+
+fn route_kind(flags: u32) -> &'static str {
+    match flags & RTF_REJECT {
+        0 => "unicast",
+        _ => "unreachable",
+    }
+}
+
+/// Parses `/proc/net/{tcp,udp}{,6}`. Addresses are hex dumps of the kernel's
+/// network-order words, ports plain hex.
+pub fn parse_proc_net_sockets(content: &str, protocol: Protocol) -> Vec<Connection> {
+    let mut connections = Vec::new();
+
+    for line in content.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 8 {
+            continue;
+        }
+        let (Some(local), Some(remote), Ok(state), Ok(uid)) = (
+            parse_socket_address(fields[1]),
+            parse_socket_address(fields[2]),
+            u8::from_str_radix(fields[3], 16),
+            fields[7].parse(),
+        ) else {
+            continue;
+        };
+        let state = match (protocol, state) {
+            (Protocol::Udp, 1) => "ESTAB",
+            (Protocol::Udp, _) => "UNCONN",
+            (Protocol::Tcp, state) => TCP_STATES.get(usize::from(state).wrapping_sub(1)).copied().unwrap_or("UNKNOWN"),
+        };
+        connections.push(Connection {
+            protocol,
+            local,
+            remote: (remote.port() != 0).then_some(remote),
+            state,
+            uid,
+        });
+    }
+    connections
+}
+
+/// `0100007F:0035` or its 32-digit IPv6 form; IPv4-mapped IPv6 addresses are
+/// shown as plain IPv4.
+fn parse_socket_address(field: &str) -> Option<SocketAddr> {
+    let (address, port) = field.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let address = match address.len() {
+        8 => IpAddr::V4(Ipv4Addr::from(u32::from_str_radix(address, 16).ok()?.to_ne_bytes())),
+        32 => {
+            let mut octets = [0u8; 16];
+            for (chunk, word) in octets.chunks_mut(4).zip(0..) {
+                let word = u32::from_str_radix(&address[word * 8..word * 8 + 8], 16).ok()?;
+                chunk.copy_from_slice(&word.to_ne_bytes());
+            }
+            let address = Ipv6Addr::from(octets);
+            address.to_ipv4_mapped().map_or(IpAddr::V6(address), IpAddr::V4)
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(address, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> LinuxCollector {
+        LinuxCollector::with_root(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/linux"))
+    }
+
+    #[test]
+    fn counters_from_proc_net_dev() {
+        let counters = fixture().counters().unwrap();
+
+        assert_eq!(counters.len(), 4);
+        assert_eq!(counters["eth0"], LinkCounters {
+            rx_bytes: 987_654_321,
+            rx_packets: 654_321,
+            rx_errors: 1,
+            rx_dropped: 2,
+            tx_bytes: 123_456_789,
+            tx_packets: 54_321,
+            tx_errors: 3,
+            tx_dropped: 4,
+        });
+        assert_eq!(counters["lo"].tx_bytes, 12_345);
+    }
+
+    #[test]
+    fn interface_name_with_colon() {
+        let counters = fixture().counters().unwrap();
+
+        assert_eq!(counters["br:lan"].rx_bytes, 4096);
+        assert_eq!(counters["br:lan"].tx_packets, 16);
+        assert!(!counters.contains_key("br"));
+    }
+
+    #[test]
+    fn short_lines_are_skipped() {
+        let counters = parse_proc_net_dev("  eth0: 1 2 3\n");
+
+        assert!(counters.is_empty());
+    }
+
+    #[test]
+    fn ipv6_flags_from_if_inet6() {
+        let flags = fixture().ipv6_flags();
+        let flags_of = |address: &str| flags[&("eth0".to_string(), address.parse().unwrap())];
+
+        assert_eq!(flags.len(), 5);
+        assert_eq!(flags_of("fe80::211:22ff:fe33:4455"), AddressFlags::default());
+        assert!(flags_of("2001:db8::10").temporary);
+        assert!(flags_of("2001:db8::20").deprecated);
+        assert!(flags_of("2001:db8::30").tentative);
+        assert!(!flags_of("2001:db8::30").deprecated);
+    }
+
+    #[test]
+    fn physical_link_details() {
+        let details = fixture().link_details("eth0");
+
+        assert_eq!(details, LinkDetails {
+            mac_address: Some("00:11:22:33:44:55".to_string()),
+            mtu: Some(1500),
+            speed: Some(1000),
+            status: true,
+            driver: Some("e1000e".to_string()),
+        });
+    }
+
+    #[test]
+    fn virtual_link_details() {
+        // Reading `speed` of a virtual link fails with EINVAL; the fixture
+        // has no such file, which fails the same way.
+        let details = fixture().link_details("wg0");
+
+        assert_eq!(details, LinkDetails {
+            mac_address: None,
+            mtu: Some(1420),
+            speed: None,
+            status: true,
+            driver: None,
+        });
+    }
+
+    #[test]
+    fn missing_link() {
+        assert_eq!(fixture().link_details("eth9"), LinkDetails::default());
+    }
+
+    fn route(family: IpFamily, destination: &str, prefix_len: u8, gateway: Option<&str>, interface: &str, metric: u32) -> Route {
+        Route {
+            family,
+            destination: destination.parse().unwrap(),
+            prefix_len,
+            gateway: gateway.map(|gateway| gateway.parse().unwrap()),
+            interface: Some(interface.to_string()),
+            metric,
+            table: "main".to_string(),
+            kind: "unicast",
+            source: None,
+        }
+    }
+
+    #[test]
+    fn ipv4_routes_from_proc_net_route() {
+        let routes = parse_proc_net_route(&fs::read_to_string(fixture().root().join("proc/net/route")).unwrap());
+
+        assert_eq!(routes, vec![
+            route(IpFamily::V4, "0.0.0.0", 0, Some("192.168.1.1"), "eth0", 100),
+            route(IpFamily::V4, "192.168.1.0", 24, None, "eth0", 100),
+            Route {
+                kind: "unreachable",
+                ..route(IpFamily::V4, "10.0.0.0", 8, None, "wg0", 0)
+            },
+        ]);
+    }
+
+    #[test]
+    fn ipv6_routes_skip_local_cached_and_catch_all_entries() {
+        let routes = parse_ipv6_route(&fs::read_to_string(fixture().root().join("proc/net/ipv6_route")).unwrap());
+
+        assert_eq!(routes, vec![
+            route(IpFamily::V6, "::", 0, Some("fe80::1"), "eth0", 1024),
+            route(IpFamily::V6, "2001:db8::", 64, None, "eth0", 256),
+            route(IpFamily::V6, "fe80::", 64, None, "eth0", 256),
+        ]);
+    }
+
+    #[test]
+    fn routes_of_both_families() {
+        let routes = fixture().routes().unwrap();
+
+        assert_eq!(routes.len(), 6);
+        assert_eq!(routes.iter().filter(|route| route.family == IpFamily::V6).count(), 3);
+    }
+
+    #[test]
+    fn sockets_from_proc_net() {
+        let connection = |protocol, local: &str, remote: Option<&str>, state, uid| Connection {
+            protocol,
+            local: local.parse().unwrap(),
+            remote: remote.map(|remote| remote.parse().unwrap()),
+            state,
+            uid,
+        };
+
+        assert_eq!(fixture().connections().unwrap(), vec![
+            connection(Protocol::Tcp, "127.0.0.53:53", None, "LISTEN", 101),
+            connection(Protocol::Tcp, "10.0.2.2:22", Some("10.0.0.100:54321"), "ESTAB", 0),
+            connection(Protocol::Tcp, "10.0.2.2:40000", Some("10.0.2.10:443"), "TIME-WAIT", 0),
+            connection(Protocol::Tcp, "[::]:80", None, "LISTEN", 33),
+            connection(Protocol::Tcp, "10.0.2.2:80", Some("192.0.2.5:50000"), "ESTAB", 33),
+            connection(Protocol::Tcp, "[2001:db8::1]:443", Some("[2001:db8::2]:58000"), "ESTAB", 1000),
+            connection(Protocol::Udp, "127.0.0.53:53", None, "UNCONN", 101),
+            connection(Protocol::Udp, "10.0.2.2:46051", Some("8.8.8.8:53"), "ESTAB", 1000),
+        ]);
+    }
+
+    #[test]
+    fn socket_addresses() {
+        assert_eq!(parse_socket_address("0100007F:1F90"), Some("127.0.0.1:8080".parse().unwrap()));
+        assert_eq!(
+            parse_socket_address("B80D0120000000000000000001000000:01BB"),
+            Some("[2001:db8::1]:443".parse().unwrap())
+        );
+        assert_eq!(
+            parse_socket_address("0000000000000000FFFF00000100007F:0035"),
+            Some("127.0.0.1:53".parse().unwrap())
+        );
+        assert_eq!(parse_socket_address("0100007F"), None);
+        assert_eq!(parse_socket_address("00007F:0035"), None);
+        assert_eq!(parse_socket_address("0100007G:0035"), None);
+        assert_eq!(parse_socket_address("0100007F:10000"), None);
+    }
+
+    #[test]
+    fn table_names_from_rt_tables() {
+        let names = fixture().table_names();
+
+        assert_eq!(names, HashMap::from([
+            (0, "unspec".to_string()),
+            (100, "isp2".to_string()),
+            (200, "tunnel".to_string()),
+            (253, "default".to_string()),
+            (254, "main".to_string()),
+            (255, "local".to_string()),
+            (51820, "wg".to_string()),
+        ]));
+    }
+}
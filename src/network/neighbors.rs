@@ -0,0 +1,14 @@
+use std::net::IpAddr;
+
+/// An ARP (IPv4) or NDP (IPv6) neighbor cache entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Neighbor {
+    pub address: IpAddr,
+    /// Link-layer address, `None` while resolution is incomplete or failed.
+    pub mac_address: Option<String>,
+    pub interface: String,
+    /// Reachability as `ip neigh` names it, e.g. `REACHABLE`, `STALE`.
+    pub state: &'static str,
+    /// Whether the neighbor announced itself as an IPv6 router.
+    pub router: bool,
+}
@@ -0,0 +1,906 @@
+use super::neighbors::Neighbor;
+use super::routes::{table_name, Route, Rule};
+use super::IpFamily;
+use anyhow::{bail, Context, Result};
+use nix::errno::Errno;
+use nix::net::if_::if_nameindex;
+use nix::sys::socket::{
+    bind, recv, send, socket, AddressFamily, MsgFlags, NetlinkAddr, SockFlag, SockProtocol, SockType,
+};
+use nix::unistd::close;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::fd::RawFd;
+use std::thread;
+use tokio::sync::mpsc::{self, error::TryRecvError, UnboundedReceiver};
+
+const RTMGRP_LINK: u32 = 0x1;
+const RTMGRP_IPV4_IFADDR: u32 = 0x10;
+const RTMGRP_IPV6_IFADDR: u32 = 0x100;
+
+const RTM_NEWLINK: u16 = 16;
+const RTM_DELLINK: u16 = 17;
+const RTM_NEWADDR: u16 = 20;
+const RTM_DELADDR: u16 = 21;
+const RTM_GETROUTE: u16 = 26;
+const RTM_GETNEIGH: u16 = 30;
+const RTM_GETRULE: u16 = 34;
+
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+/// Message types below this are netlink's own control messages.
+const NLMSG_MIN_TYPE: u16 = 0x10;
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_DUMP: u16 = 0x300;
+
+const NLMSG_HDRLEN: usize = 16;
+/// `struct ndmsg`, `struct rtmsg` and `struct fib_rule_hdr` are all 12 bytes.
+const FAMILY_HDRLEN: usize = 12;
+
+const AF_INET: u8 = 2;
+const AF_INET6: u8 = 10;
+
+const NDA_DST: u16 = 1;
+const NDA_LLADDR: u16 = 2;
+const NTF_ROUTER: u8 = 0x80;
+const NUD_NOARP: u16 = 0x40;
+
+/// `NUD_*` bits as `ip neigh` names them.
+const NUD_STATES: &[(u16, &str)] = &[
+    (0x01, "INCOMPLETE"),
+    (0x02, "REACHABLE"),
+    (0x04, "STALE"),
+    (0x08, "DELAY"),
+    (0x10, "PROBE"),
+    (0x20, "FAILED"),
+    (0x80, "PERMANENT"),
+];
+
+const RTA_DST: u16 = 1;
+const RTA_SRC: u16 = 2;
+const RTA_OIF: u16 = 4;
+const RTA_GATEWAY: u16 = 5;
+const RTA_PRIORITY: u16 = 6;
+const RTA_PREFSRC: u16 = 7;
+const RTA_MULTIPATH: u16 = 9;
+const RTA_TABLE: u16 = 15;
+const RTM_F_CLONED: u32 = 0x200;
+const RT_TABLE_LOCAL: u32 = 255;
+/// `struct rtnexthop`: length, flags, hops and ifindex.
+const RTNH_LEN: usize = 8;
+
+/// `RTN_*` route types as `ip route` names them, indexed by value.
+const ROUTE_KINDS: [&str; 12] = [
+    "unspec", "unicast", "local", "broadcast", "anycast", "multicast",
+    "blackhole", "unreachable", "prohibit", "throw", "nat", "xresolve",
+];
+
+const FRA_DST: u16 = 1;
+const FRA_SRC: u16 = 2;
+const FRA_IIFNAME: u16 = 3;
+const FRA_GOTO: u16 = 4;
+const FRA_PRIORITY: u16 = 6;
+const FRA_FWMARK: u16 = 10;
+const FRA_TABLE: u16 = 15;
+const FRA_FWMASK: u16 = 16;
+const FRA_OIFNAME: u16 = 17;
+const FRA_SUPPRESS_PREFIXLEN: u16 = 14;
+const FRA_L3MDEV: u16 = 19;
+const FRA_UID_RANGE: u16 = 20;
+const FIB_RULE_INVERT: u32 = 0x2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkEvent {
+    NewLink,
+    DelLink,
+    NewAddr,
+    DelAddr,
+    /// The socket buffer overflowed during a burst and notifications were
+    /// lost, so what changed is unknown.
+    Overrun,
+}
+
+/// Listens for rtnetlink link and address notifications on a background
+/// thread so interfaces can be rediscovered as soon as they change.
+pub struct LinkWatcher {
+    events: UnboundedReceiver<LinkEvent>,
+    stopped: bool,
+}
+
+impl LinkWatcher {
+    pub fn spawn() -> Result<Self> {
+        let fd = socket(
+            AddressFamily::Netlink,
+            SockType::Raw,
+            SockFlag::SOCK_CLOEXEC,
+            SockProtocol::NetlinkRoute,
+        )
+        .context("Failed to open rtnetlink socket")?;
+
+        let groups = RTMGRP_LINK | RTMGRP_IPV4_IFADDR | RTMGRP_IPV6_IFADDR;
+        if let Err(e) = bind(fd, &NetlinkAddr::new(0, groups)) {
+            let _ = close(fd);
+            return Err(e).context("Failed to subscribe to rtnetlink groups");
+        }
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        thread::Builder::new()
+            .name("rtnetlink".to_string())
+            .spawn(move || {
+                let mut buf = vec![0u8; 16 * 1024];
+                'listen: loop {
+                    let events = match recv(fd, &mut buf, MsgFlags::empty()) {
+                        Ok(len) => parse_messages(&buf[..len]),
+                        Err(Errno::EINTR) => continue,
+                        // The socket stays usable after an overflow; only the
+                        // dropped notifications need one rescan to make up for.
+                        Err(Errno::ENOBUFS) => vec![LinkEvent::Overrun],
+                        Err(_) => break,
+                    };
+                    for event in events {
+                        if tx.send(event).is_err() {
+                            break 'listen;
+                        }
+                    }
+                }
+                let _ = close(fd);
+            })
+            .context("Failed to start rtnetlink thread")?;
+
+        Ok(LinkWatcher { events: rx, stopped: false })
+    }
+
+    /// Drains pending notifications, returning whether any arrived since the
+    /// last call. When the listener thread has stopped this reports one last
+    /// change and `stopped` turns true.
+    pub fn changed(&mut self) -> bool {
+        let mut changed = false;
+        loop {
+            match self.events.try_recv() {
+                Ok(_) => changed = true,
+                Err(TryRecvError::Empty) => return changed,
+                Err(TryRecvError::Disconnected) => {
+                    let first = !self.stopped;
+                    self.stopped = true;
+                    return changed || first;
+                }
+            }
+        }
+    }
+
+    /// Whether the listener thread has stopped, after which no change is ever
+    /// reported again and callers have to rediscover interfaces themselves.
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+}
+
+/// Dumps the ARP and NDP neighbor caches. `NOARP` entries, which the kernel
+/// keeps for multicast and point-to-point peers, are left out like `ip neigh`
+/// does.
+pub fn neighbors() -> Result<Vec<Neighbor>> {
+    let messages = dump(RTM_GETNEIGH, "neighbor table")?;
+    let names = interface_names();
+    Ok(messages
+        .iter()
+        .filter_map(|message| parse_neighbor(message, &names))
+        .collect())
+}
+
+/// Dumps every routing table of both families but `local`, which only holds
+/// the host's own and broadcast addresses. `tables` names the table ids.
+pub fn routes(tables: &HashMap<u32, String>) -> Result<Vec<Route>> {
+    let messages = dump(RTM_GETROUTE, "routing tables")?;
+    let names = interface_names();
+    Ok(messages
+        .iter()
+        .filter_map(|message| parse_route(message, &names, tables))
+        .collect())
+}
+
+/// Dumps the policy routing rules of both families.
+pub fn rules(tables: &HashMap<u32, String>) -> Result<Vec<Rule>> {
+    let messages = dump(RTM_GETRULE, "routing rules")?;
+    Ok(messages
+        .iter()
+        .filter_map(|message| parse_rule(message, tables))
+        .collect())
+}
+
+/// The index of the interface the kernel routes a packet from `source` to
+/// `destination` through, as `ip route get <destination> from <source>`
+/// shows, so source-based rules are taken into account.
+pub fn output_interface(destination: IpAddr, source: IpAddr) -> Result<Option<u32>> {
+    let (family, bits) = match destination {
+        IpAddr::V4(_) => (AF_INET, 32),
+        IpAddr::V6(_) => (AF_INET6, 128),
+    };
+    let mut body = vec![0; FAMILY_HDRLEN];
+    body[..3].copy_from_slice(&[family, bits, bits]);
+    for (kind, address) in [(RTA_DST, destination), (RTA_SRC, source)] {
+        let octets = match address {
+            IpAddr::V4(address) => address.octets().to_vec(),
+            IpAddr::V6(address) => address.octets().to_vec(),
+        };
+        body.extend_from_slice(&(4 + octets.len() as u16).to_ne_bytes());
+        body.extend_from_slice(&kind.to_ne_bytes());
+        body.extend_from_slice(&octets);
+    }
+
+    let messages = exchange(RTM_GETROUTE, NLM_F_REQUEST, &body)
+        .with_context(|| format!("Failed to look up the route from {} to {}", source, destination))?;
+    Ok(messages.first().and_then(|message| {
+        attributes(message)
+            .into_iter()
+            .find(|(kind, _)| *kind == RTA_OIF)
+            .and_then(|(_, payload)| u32_value(payload))
+    }))
+}
+
+/// Sends a dump request of `kind` for every address family and collects the
+/// payloads of the replies until the kernel signals the end of the dump.
+fn dump(kind: u16, what: &str) -> Result<Vec<Vec<u8>>> {
+    exchange(kind, NLM_F_REQUEST | NLM_F_DUMP, &[0; FAMILY_HDRLEN]).with_context(|| format!("Failed to dump the {}", what))
+}
+
+/// Sends one request of `kind` with `body` after the netlink header and
+/// collects the payloads of the replies: up to the end of a dump, or the
+/// single answer to any other request.
+fn exchange(kind: u16, flags: u16, body: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let fd = socket(
+        AddressFamily::Netlink,
+        SockType::Raw,
+        SockFlag::SOCK_CLOEXEC,
+        SockProtocol::NetlinkRoute,
+    )
+    .context("Failed to open rtnetlink socket")?;
+
+    let result = exchange_on(fd, kind, flags, body);
+    let _ = close(fd);
+    result
+}
+
+fn exchange_on(fd: RawFd, kind: u16, flags: u16, body: &[u8]) -> Result<Vec<Vec<u8>>> {
+    bind(fd, &NetlinkAddr::new(0, 0))?;
+
+    let mut request = Vec::with_capacity(NLMSG_HDRLEN + body.len());
+    request.extend_from_slice(&((NLMSG_HDRLEN + body.len()) as u32).to_ne_bytes());
+    request.extend_from_slice(&kind.to_ne_bytes());
+    request.extend_from_slice(&flags.to_ne_bytes());
+    request.extend_from_slice(&1u32.to_ne_bytes());
+    request.extend_from_slice(&0u32.to_ne_bytes());
+    request.extend_from_slice(body);
+    send(fd, &request, MsgFlags::empty())?;
+
+    let mut messages = Vec::new();
+    let mut buf = vec![0u8; 32 * 1024];
+    loop {
+        let len = recv(fd, &mut buf, MsgFlags::empty())?;
+        let mut offset = 0;
+        while offset + NLMSG_HDRLEN <= len {
+            let size = u32::from_ne_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]]) as usize;
+            let kind = u16::from_ne_bytes([buf[offset + 4], buf[offset + 5]]);
+            if size < NLMSG_HDRLEN || offset + size > len {
+                break;
+            }
+            let payload = &buf[offset + NLMSG_HDRLEN..offset + size];
+            match kind {
+                NLMSG_DONE => return Ok(messages),
+                NLMSG_ERROR => {
+                    let code = payload.get(..4).map_or(0, |code| i32::from_ne_bytes([code[0], code[1], code[2], code[3]]));
+                    bail!("{}", Errno::from_i32(-code).desc());
+                }
+                kind if kind >= NLMSG_MIN_TYPE => messages.push(payload.to_vec()),
+                _ => {}
+            }
+            offset += (size + 3) & !3;
+        }
+        if flags & NLM_F_DUMP == 0 && !messages.is_empty() {
+            return Ok(messages);
+        }
+    }
+}
+
+/// Splits the attributes following a message's fixed header into type and
+/// payload, dropping the nested and byte-order flag bits from the type.
+fn attributes(message: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut attributes = Vec::new();
+    let mut offset = FAMILY_HDRLEN;
+    while offset + 4 <= message.len() {
+        let len = u16::from_ne_bytes([message[offset], message[offset + 1]]) as usize;
+        let kind = u16::from_ne_bytes([message[offset + 2], message[offset + 3]]) & 0x3fff;
+        if len < 4 || offset + len > message.len() {
+            break;
+        }
+        attributes.push((kind, &message[offset + 4..offset + len]));
+        offset += (len + 3) & !3;
+    }
+    attributes
+}
+
+fn interface_names() -> HashMap<u32, String> {
+    if_nameindex()
+        .map(|interfaces| {
+            interfaces
+                .iter()
+                .map(|interface| (interface.index(), interface.name().to_string_lossy().into_owned()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn interface_name(index: u32, names: &HashMap<u32, String>) -> String {
+    names.get(&index).cloned().unwrap_or_else(|| index.to_string())
+}
+
+fn ip_address(payload: &[u8]) -> Option<IpAddr> {
+    match payload.len() {
+        4 => Some(IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(payload).ok()?))),
+        16 => Some(IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(payload).ok()?))),
+        _ => None,
+    }
+}
+
+fn u32_value(payload: &[u8]) -> Option<u32> {
+    Some(u32::from_ne_bytes(payload.get(..4)?.try_into().ok()?))
+}
+
+fn family(value: u8) -> Option<IpFamily> {
+    match value {
+        AF_INET => Some(IpFamily::V4),
+        AF_INET6 => Some(IpFamily::V6),
+        _ => None,
+    }
+}
+
+/// Decodes one `ndmsg` with its `NDA_DST` and `NDA_LLADDR` attributes.
+fn parse_neighbor(message: &[u8], names: &HashMap<u32, String>) -> Option<Neighbor> {
+    let header = message.get(..FAMILY_HDRLEN)?;
+    let index = u32::from_ne_bytes([header[4], header[5], header[6], header[7]]);
+    let state = u16::from_ne_bytes([header[8], header[9]]);
+    let flags = header[10];
+    if state & NUD_NOARP != 0 {
+        return None;
+    }
+
+    let mut address = None;
+    let mut mac_address = None;
+    for (kind, payload) in attributes(message) {
+        match kind {
+            NDA_DST => address = ip_address(payload),
+            NDA_LLADDR if !payload.is_empty() => {
+                let octets: Vec<String> = payload.iter().map(|octet| format!("{:02x}", octet)).collect();
+                mac_address = Some(octets.join(":"));
+            }
+            _ => {}
+        }
+    }
+
+    let state = NUD_STATES
+        .iter()
+        .find(|(bit, _)| state & bit != 0)
+        .map_or("NONE", |(_, name)| name);
+    Some(Neighbor {
+        address: address?,
+        mac_address,
+        interface: interface_name(index, names),
+        state,
+        router: flags & NTF_ROUTER != 0,
+    })
+}
+
+/// Decodes one `rtmsg`. Cached clones and the `local` table are skipped; a
+/// multipath route is shown by its first next hop.
+fn parse_route(message: &[u8], names: &HashMap<u32, String>, tables: &HashMap<u32, String>) -> Option<Route> {
+    let header = message.get(..FAMILY_HDRLEN)?;
+    let family = family(header[0])?;
+    let flags = u32::from_ne_bytes([header[8], header[9], header[10], header[11]]);
+    if flags & RTM_F_CLONED != 0 {
+        return None;
+    }
+
+    let mut table = u32::from(header[4]);
+    let mut destination = None;
+    let mut gateway = None;
+    let mut index = None;
+    let mut metric = 0;
+    let mut source = None;
+    for (kind, payload) in attributes(message) {
+        match kind {
+            RTA_DST => destination = ip_address(payload),
+            RTA_GATEWAY => gateway = ip_address(payload),
+            RTA_OIF => index = u32_value(payload),
+            RTA_PRIORITY => metric = u32_value(payload).unwrap_or(0),
+            RTA_PREFSRC => source = ip_address(payload),
+            RTA_TABLE => table = u32_value(payload).unwrap_or(table),
+            RTA_MULTIPATH if payload.len() >= RTNH_LEN => {
+                index = index.or(u32_value(&payload[4..]));
+                let hop_len = usize::from(u16::from_ne_bytes([payload[0], payload[1]])).min(payload.len());
+                let mut offset = RTNH_LEN;
+                while offset + 4 <= hop_len {
+                    let len = usize::from(u16::from_ne_bytes([payload[offset], payload[offset + 1]]));
+                    let kind = u16::from_ne_bytes([payload[offset + 2], payload[offset + 3]]);
+                    if len < 4 || offset + len > hop_len {
+                        break;
+                    }
+                    if kind == RTA_GATEWAY {
+                        gateway = gateway.or(ip_address(&payload[offset + 4..offset + len]));
+                    }
+                    offset += (len + 3) & !3;
+                }
+            }
+            _ => {}
+        }
+    }
+    if table == RT_TABLE_LOCAL {
+        return None;
+    }
+
+    let destination = destination.unwrap_or(match family {
+        IpFamily::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpFamily::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    });
+    Some(Route {
+        family,
+        destination,
+        prefix_len: header[1],
+        gateway,
+        interface: index.map(|index| interface_name(index, names)),
+        metric,
+        table: table_name(table, tables),
+        kind: ROUTE_KINDS.get(usize::from(header[7])).copied().unwrap_or("unknown"),
+        source,
+    })
+}
+
+/// Decodes one `fib_rule_hdr` into the selector and action `ip rule` shows.
+fn parse_rule(message: &[u8], tables: &HashMap<u32, String>) -> Option<Rule> {
+    let header = message.get(..FAMILY_HDRLEN)?;
+    let family = family(header[0])?;
+    let (dst_len, src_len, action) = (header[1], header[2], header[7]);
+    let flags = u32::from_ne_bytes([header[8], header[9], header[10], header[11]]);
+
+    let mut table = u32::from(header[4]);
+    let mut priority = 0;
+    let mut from = None;
+    let mut to = None;
+    let mut iif = None;
+    let mut oif = None;
+    let mut fwmark = None;
+    let mut fwmask = None;
+    let mut goto = None;
+    let mut suppress_prefixlen = None;
+    let mut l3mdev = false;
+    let mut uid_range = None;
+    for (kind, payload) in attributes(message) {
+        let name = || String::from_utf8_lossy(payload).trim_end_matches('\0').to_string();
+        match kind {
+            FRA_SRC => from = ip_address(payload),
+            FRA_DST => to = ip_address(payload),
+            FRA_IIFNAME => iif = Some(name()),
+            FRA_OIFNAME => oif = Some(name()),
+            FRA_PRIORITY => priority = u32_value(payload).unwrap_or(0),
+            FRA_FWMARK => fwmark = u32_value(payload),
+            FRA_FWMASK => fwmask = u32_value(payload),
+            FRA_TABLE => table = u32_value(payload).unwrap_or(table),
+            FRA_GOTO => goto = u32_value(payload),
+            // The kernel always sends this one, with -1 when it is unset.
+            FRA_SUPPRESS_PREFIXLEN => suppress_prefixlen = u32_value(payload).filter(|&length| length != u32::MAX),
+            FRA_L3MDEV => l3mdev = payload.first().is_some_and(|&value| value != 0),
+            FRA_UID_RANGE => uid_range = u32_value(payload).zip(payload.get(4..).and_then(u32_value)),
+            _ => {}
+        }
+    }
+
+    let mut selector = Vec::new();
+    if flags & FIB_RULE_INVERT != 0 {
+        selector.push("not".to_string());
+    }
+    selector.push(match from {
+        Some(from) => format!("from {}/{}", from, src_len),
+        None => "from all".to_string(),
+    });
+    if let Some(to) = to {
+        selector.push(format!("to {}/{}", to, dst_len));
+    }
+    match (fwmark, fwmask) {
+        (Some(mark), Some(mask)) if mask != u32::MAX => selector.push(format!("fwmark {:#x}/{:#x}", mark, mask)),
+        (Some(mark), _) => selector.push(format!("fwmark {:#x}", mark)),
+        (None, _) => {}
+    }
+    selector.extend(iif.map(|iif| format!("iif {}", iif)));
+    selector.extend(oif.map(|oif| format!("oif {}", oif)));
+    selector.extend(uid_range.map(|(start, end)| format!("uidrange {}-{}", start, end)));
+
+    let mut action = match action {
+        1 if l3mdev => "lookup [l3mdev-table]".to_string(),
+        1 => format!("lookup {}", table_name(table, tables)),
+        2 => goto.map_or_else(|| "goto".to_string(), |target| format!("goto {}", target)),
+        3 => "nop".to_string(),
+        6 => "blackhole".to_string(),
+        7 => "unreachable".to_string(),
+        8 => "prohibit".to_string(),
+        other => format!("action {}", other),
+    };
+    if let Some(length) = suppress_prefixlen {
+        action.push_str(&format!(" suppress_prefixlength {}", length));
+    }
+    Some(Rule {
+        family,
+        priority,
+        selector: selector.join(" "),
+        action,
+    })
+}
+
+pub fn parse_messages(buf: &[u8]) -> Vec<LinkEvent> {
+    let mut events = Vec::new();
+    let mut offset = 0;
+
+    while offset + NLMSG_HDRLEN <= buf.len() {
+        let len = u32::from_ne_bytes([buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]]) as usize;
+        let kind = u16::from_ne_bytes([buf[offset + 4], buf[offset + 5]]);
+        if len < NLMSG_HDRLEN {
+            break;
+        }
+
+        match kind {
+            RTM_NEWLINK => events.push(LinkEvent::NewLink),
+            RTM_DELLINK => events.push(LinkEvent::DelLink),
+            RTM_NEWADDR => events.push(LinkEvent::NewAddr),
+            RTM_DELADDR => events.push(LinkEvent::DelAddr),
+            _ => {}
+        }
+
+        offset += (len + 3) & !3;
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watcher() -> (mpsc::UnboundedSender<LinkEvent>, LinkWatcher) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (tx, LinkWatcher { events: rx, stopped: false })
+    }
+
+    #[test]
+    fn changes_are_drained() {
+        let (tx, mut watcher) = watcher();
+
+        assert!(!watcher.changed());
+        tx.send(LinkEvent::NewAddr).unwrap();
+        tx.send(LinkEvent::Overrun).unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+        assert!(!watcher.stopped());
+    }
+
+    #[test]
+    fn stopped_listener_reports_one_change() {
+        let (tx, mut watcher) = watcher();
+        drop(tx);
+
+        assert!(watcher.changed());
+        assert!(watcher.stopped());
+        assert!(!watcher.changed());
+    }
+
+    const RTM_NEWROUTE: u16 = 24;
+
+    /// A route, neighbor or rule message: its 12-byte family header followed
+    /// by padded attributes.
+    fn message(header: [u8; FAMILY_HDRLEN], attributes: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut message = header.to_vec();
+        for (kind, payload) in attributes {
+            message.extend_from_slice(&(4 + payload.len() as u16).to_ne_bytes());
+            message.extend_from_slice(&kind.to_ne_bytes());
+            message.extend_from_slice(payload);
+            message.resize((message.len() + 3) & !3, 0);
+        }
+        message
+    }
+
+    /// `struct rtmsg` with the flags word last.
+    fn rtmsg(family: u8, dst_len: u8, table: u8, kind: u8, flags: u32) -> [u8; FAMILY_HDRLEN] {
+        let mut header = [family, dst_len, 0, 0, table, 3, 0, kind, 0, 0, 0, 0];
+        header[8..].copy_from_slice(&flags.to_ne_bytes());
+        header
+    }
+
+    /// `struct ndmsg`: family, padding, ifindex, state, flags and type.
+    fn ndmsg(family: u8, index: u32, state: u16, flags: u8) -> [u8; FAMILY_HDRLEN] {
+        let mut header = [family, 0, 0, 0, 0, 0, 0, 0, 0, 0, flags, 1];
+        header[4..8].copy_from_slice(&index.to_ne_bytes());
+        header[8..10].copy_from_slice(&state.to_ne_bytes());
+        header
+    }
+
+    fn names() -> HashMap<u32, String> {
+        HashMap::from([(2, "eth0".to_string()), (3, "wg0".to_string())])
+    }
+
+    fn tables() -> HashMap<u32, String> {
+        HashMap::from([(254, "main".to_string())])
+    }
+
+    #[test]
+    fn default_route() {
+        let message = message(rtmsg(AF_INET, 0, 254, 1, 0), &[
+            (RTA_TABLE, &254u32.to_ne_bytes()),
+            (RTA_PRIORITY, &100u32.to_ne_bytes()),
+            (RTA_GATEWAY, &[192, 0, 2, 1]),
+            (RTA_OIF, &2u32.to_ne_bytes()),
+            (RTA_PREFSRC, &[192, 0, 2, 10]),
+        ]);
+
+        assert_eq!(parse_route(&message, &names(), &tables()), Some(Route {
+            family: IpFamily::V4,
+            destination: "0.0.0.0".parse().unwrap(),
+            prefix_len: 0,
+            gateway: Some("192.0.2.1".parse().unwrap()),
+            interface: Some("eth0".to_string()),
+            metric: 100,
+            table: "main".to_string(),
+            kind: "unicast",
+            source: Some("192.0.2.10".parse().unwrap()),
+        }));
+    }
+
+    #[test]
+    fn unreachable_ipv6_route_in_a_numbered_table() {
+        let destination: Ipv6Addr = "2001:db8:1::".parse().unwrap();
+        let message = message(rtmsg(AF_INET6, 48, 0, 7, 0), &[
+            (RTA_TABLE, &1000u32.to_ne_bytes()),
+            (RTA_DST, &destination.octets()),
+            (RTA_OIF, &9u32.to_ne_bytes()),
+        ]);
+
+        let route = parse_route(&message, &names(), &tables()).unwrap();
+        assert_eq!(route.destination, IpAddr::V6(destination));
+        assert_eq!(route.prefix_len, 48);
+        assert_eq!(route.kind, "unreachable");
+        assert_eq!(route.table, "1000");
+        assert_eq!(route.interface.as_deref(), Some("9"));
+        assert_eq!(route.gateway, None);
+    }
+
+    #[test]
+    fn local_cloned_and_foreign_routes_are_skipped() {
+        let local = message(rtmsg(AF_INET, 32, 255, 2, 0), &[(RTA_DST, &[127, 0, 0, 1])]);
+        let local_by_attribute = message(rtmsg(AF_INET, 32, 252, 2, 0), &[(RTA_TABLE, &255u32.to_ne_bytes())]);
+        let cloned = message(rtmsg(AF_INET6, 128, 254, 1, RTM_F_CLONED), &[]);
+        let bridge = message(rtmsg(7, 0, 254, 1, 0), &[]);
+
+        for message in [local, local_by_attribute, cloned, bridge] {
+            assert_eq!(parse_route(&message, &names(), &tables()), None);
+        }
+        assert_eq!(parse_route(&[AF_INET, 0, 0], &names(), &tables()), None);
+    }
+
+    #[test]
+    fn truncated_attributes_are_ignored() {
+        let mut message = message(rtmsg(AF_INET, 0, 254, 1, 0), &[(RTA_OIF, &2u32.to_ne_bytes())]);
+        message.extend_from_slice(&[20, 0, 5, 0, 192, 0]);
+
+        let route = parse_route(&message, &names(), &tables()).unwrap();
+        assert_eq!(route.interface.as_deref(), Some("eth0"));
+        assert_eq!(route.gateway, None);
+    }
+
+    #[test]
+    fn reachable_neighbor() {
+        let message = message(ndmsg(AF_INET, 2, 0x02, 0), &[
+            (NDA_DST, &[192, 0, 2, 1]),
+            (NDA_LLADDR, &[0x00, 0x11, 0x22, 0xaa, 0xbb, 0xcc]),
+        ]);
+
+        assert_eq!(parse_neighbor(&message, &names()), Some(Neighbor {
+            address: "192.0.2.1".parse().unwrap(),
+            mac_address: Some("00:11:22:aa:bb:cc".to_string()),
+            interface: "eth0".to_string(),
+            state: "REACHABLE",
+            router: false,
+        }));
+    }
+
+    #[test]
+    fn router_and_unresolved_neighbors() {
+        let router: Ipv6Addr = "fe80::1".parse().unwrap();
+        let stale_router = message(ndmsg(AF_INET6, 3, 0x04, NTF_ROUTER), &[
+            (NDA_DST, &router.octets()),
+            (NDA_LLADDR, &[0x02, 0, 0, 0, 0, 0x01]),
+        ]);
+        let incomplete = message(ndmsg(AF_INET, 7, 0x01, 0), &[(NDA_DST, &[192, 0, 2, 99]), (NDA_LLADDR, &[])]);
+        let none = message(ndmsg(AF_INET, 2, 0, 0), &[(NDA_DST, &[192, 0, 2, 98])]);
+
+        let stale_router = parse_neighbor(&stale_router, &names()).unwrap();
+        assert!(stale_router.router);
+        assert_eq!((stale_router.state, stale_router.interface.as_str()), ("STALE", "wg0"));
+
+        let incomplete = parse_neighbor(&incomplete, &names()).unwrap();
+        assert_eq!(incomplete.mac_address, None);
+        assert_eq!((incomplete.state, incomplete.interface.as_str()), ("INCOMPLETE", "7"));
+
+        assert_eq!(parse_neighbor(&none, &names()).unwrap().state, "NONE");
+    }
+
+    #[test]
+    fn noarp_and_addressless_neighbors_are_skipped() {
+        let multicast = message(ndmsg(AF_INET, 2, NUD_NOARP, 0), &[(NDA_DST, &[224, 0, 0, 251])]);
+        let addressless = message(ndmsg(AF_INET, 2, 0x02, 0), &[(NDA_LLADDR, &[0x00, 0x11, 0x22, 0xaa, 0xbb, 0xcc])]);
+
+        assert_eq!(parse_neighbor(&multicast, &names()), None);
+        assert_eq!(parse_neighbor(&addressless, &names()), None);
+    }
+
+    /// A notification as read from the socket: a netlink header and a
+    /// payload of `len` bytes.
+    fn notification(kind: u16, len: usize) -> Vec<u8> {
+        let mut buf = ((NLMSG_HDRLEN + len) as u32).to_ne_bytes().to_vec();
+        buf.extend_from_slice(&kind.to_ne_bytes());
+        buf.extend_from_slice(&[0; 10]);
+        buf.resize((buf.len() + len + 3) & !3, 0);
+        buf
+    }
+
+    #[test]
+    fn notifications_in_one_read() {
+        let buf = [
+            notification(RTM_NEWLINK, 32),
+            notification(RTM_NEWROUTE, 28),
+            notification(RTM_NEWADDR, 13),
+            notification(RTM_DELADDR, 8),
+            notification(RTM_DELLINK, 0),
+        ]
+        .concat();
+
+        assert_eq!(parse_messages(&buf), vec![
+            LinkEvent::NewLink,
+            LinkEvent::NewAddr,
+            LinkEvent::DelAddr,
+            LinkEvent::DelLink,
+        ]);
+    }
+
+    #[test]
+    fn malformed_headers_end_the_read() {
+        let mut buf = notification(RTM_NEWADDR, 8);
+        buf.extend_from_slice(&8u32.to_ne_bytes());
+        buf.extend_from_slice(&RTM_DELADDR.to_ne_bytes());
+        buf.extend_from_slice(&[0; 10]);
+        buf.extend(notification(RTM_DELLINK, 0));
+
+        assert_eq!(parse_messages(&buf), vec![LinkEvent::NewAddr]);
+        assert_eq!(parse_messages(&notification(RTM_NEWLINK, 0)[..NLMSG_HDRLEN - 1]), vec![]);
+    }
+
+    #[test]
+    fn multipath_default_route_shows_its_first_hop() {
+        fn next_hop(index: u32, gateway: [u8; 4]) -> Vec<u8> {
+            let mut hop = 16u16.to_ne_bytes().to_vec();
+            hop.extend_from_slice(&[0, 0]);
+            hop.extend_from_slice(&index.to_ne_bytes());
+            hop.extend_from_slice(&8u16.to_ne_bytes());
+            hop.extend_from_slice(&RTA_GATEWAY.to_ne_bytes());
+            hop.extend_from_slice(&gateway);
+            hop
+        }
+        let hops = [next_hop(3, [10, 0, 0, 1]), next_hop(2, [192, 0, 2, 1])].concat();
+        let multipath = message(rtmsg(AF_INET, 0, 254, 1, 0), &[(RTA_PRIORITY, &50u32.to_ne_bytes()), (RTA_MULTIPATH, &hops)]);
+
+        let route = parse_route(&multipath, &names(), &tables()).unwrap();
+        assert!(route.is_default());
+        assert_eq!(route.interface.as_deref(), Some("wg0"));
+        assert_eq!(route.gateway, Some("10.0.0.1".parse().unwrap()));
+        assert_eq!(route.metric, 50);
+
+        let truncated = message(rtmsg(AF_INET, 0, 254, 1, 0), &[(RTA_MULTIPATH, &hops[..6])]);
+        assert_eq!(parse_route(&truncated, &names(), &tables()).unwrap().interface, None);
+    }
+
+    /// `struct fib_rule_hdr`: family, lengths, tos, table, action and flags.
+    fn rule_header(family: u8, dst_len: u8, src_len: u8, action: u8, flags: u32) -> [u8; FAMILY_HDRLEN] {
+        let mut header = [family, dst_len, src_len, 0, 0, 0, 0, action, 0, 0, 0, 0];
+        header[8..].copy_from_slice(&flags.to_ne_bytes());
+        header
+    }
+
+    fn rule_tables() -> HashMap<u32, String> {
+        HashMap::from([(254, "main".to_string()), (51820, "wg".to_string())])
+    }
+
+    #[test]
+    fn lookup_rules() {
+        let main = message(rule_header(AF_INET, 0, 0, 1, 0), &[
+            (FRA_PRIORITY, &32766u32.to_ne_bytes()),
+            (FRA_TABLE, &254u32.to_ne_bytes()),
+        ]);
+        let wireguard = message(rule_header(AF_INET6, 0, 0, 1, FIB_RULE_INVERT), &[
+            (FRA_PRIORITY, &32764u32.to_ne_bytes()),
+            (FRA_FWMARK, &0xca6cu32.to_ne_bytes()),
+            (FRA_FWMASK, &u32::MAX.to_ne_bytes()),
+            (FRA_TABLE, &51820u32.to_ne_bytes()),
+        ]);
+
+        assert_eq!(parse_rule(&main, &rule_tables()), Some(Rule {
+            family: IpFamily::V4,
+            priority: 32766,
+            selector: "from all".to_string(),
+            action: "lookup main".to_string(),
+        }));
+        assert_eq!(parse_rule(&wireguard, &rule_tables()), Some(Rule {
+            family: IpFamily::V6,
+            priority: 32764,
+            selector: "not from all fwmark 0xca6c".to_string(),
+            action: "lookup wg".to_string(),
+        }));
+    }
+
+    #[test]
+    fn rule_selectors() {
+        let message = message(rule_header(AF_INET, 24, 8, 2, 0), &[
+            (FRA_SRC, &[10, 0, 0, 0]),
+            (FRA_DST, &[192, 0, 2, 0]),
+            (FRA_FWMARK, &0x1u32.to_ne_bytes()),
+            (FRA_FWMASK, &0xffu32.to_ne_bytes()),
+            (FRA_IIFNAME, b"eth0\0"),
+            (FRA_OIFNAME, b"wg0\0"),
+            (FRA_GOTO, &32000u32.to_ne_bytes()),
+        ]);
+
+        let rule = parse_rule(&message, &rule_tables()).unwrap();
+        assert_eq!(rule.selector, "from 10.0.0.0/8 to 192.0.2.0/24 fwmark 0x1/0xff iif eth0 oif wg0");
+        assert_eq!(rule.action, "goto 32000");
+        assert_eq!(rule.priority, 0);
+    }
+
+    #[test]
+    fn rule_extensions() {
+        // What wg-quick installs next to its fwmark rule.
+        let suppress = message(rule_header(AF_INET6, 0, 0, 1, 0), &[
+            (FRA_PRIORITY, &32765u32.to_ne_bytes()),
+            (FRA_TABLE, &254u32.to_ne_bytes()),
+            (FRA_SUPPRESS_PREFIXLEN, &0u32.to_ne_bytes()),
+        ]);
+        let unset = message(rule_header(AF_INET, 0, 0, 1, 0), &[
+            (FRA_TABLE, &254u32.to_ne_bytes()),
+            (FRA_SUPPRESS_PREFIXLEN, &u32::MAX.to_ne_bytes()),
+        ]);
+        let vrf = message(rule_header(AF_INET, 0, 0, 1, 0), &[(FRA_L3MDEV, &[1])]);
+        let uid_range = [1000u32.to_ne_bytes(), 1999u32.to_ne_bytes()].concat();
+        let uids = message(rule_header(AF_INET, 0, 0, 1, 0), &[
+            (FRA_TABLE, &51820u32.to_ne_bytes()),
+            (FRA_UID_RANGE, &uid_range),
+        ]);
+
+        let rule = parse_rule(&suppress, &rule_tables()).unwrap();
+        assert_eq!(rule.selector, "from all");
+        assert_eq!(rule.action, "lookup main suppress_prefixlength 0");
+        assert_eq!(parse_rule(&unset, &rule_tables()).unwrap().action, "lookup main");
+        assert_eq!(parse_rule(&vrf, &rule_tables()).unwrap().action, "lookup [l3mdev-table]");
+
+        let rule = parse_rule(&uids, &rule_tables()).unwrap();
+        assert_eq!(rule.selector, "from all uidrange 1000-1999");
+        assert_eq!(rule.action, "lookup wg");
+    }
+
+    #[test]
+    fn rule_actions() {
+        let action = |action: u8| {
+            let message = message(rule_header(AF_INET, 0, 0, action, 0), &[]);
+            parse_rule(&message, &rule_tables()).unwrap().action
+        };
+
+        assert_eq!(action(1), "lookup 0");
+        assert_eq!(action(2), "goto");
+        assert_eq!(action(3), "nop");
+        assert_eq!(action(6), "blackhole");
+        assert_eq!(action(7), "unreachable");
+        assert_eq!(action(8), "prohibit");
+        assert_eq!(action(9), "action 9");
+        assert_eq!(parse_rule(&message(rule_header(7, 0, 0, 1, 0), &[]), &rule_tables()), None);
+    }
+}
@@ -0,0 +1,894 @@
+use super::dns::{self, Record, RecordType, DNS_PORT};
+use super::egress::Egress;
+use super::geoip::GeoInfo;
+use super::IpFamily;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::VecDeque;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::timeout;
+
+pub const DEFAULT_PROVIDERS: &[&str] = &[
+    "https://api.ipify.org",
+    "https://ifconfig.me/ip",
+    "https://icanhazip.com",
+    "https://ipinfo.io/ip",
+    "https://myexternalip.com/raw",
+    "dns://resolver1.opendns.com/myip.opendns.com",
+];
+
+/// Upper bound on a whole lookup, however many providers it tries.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// Number of public address changes remembered.
+const HISTORY_LEN: usize = 32;
+
+/// Longest slice of an invalid response body quoted in a provider error.
+const BODY_EXCERPT_LEN: usize = 40;
+
+/// How a provider's answer carries the address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResponseFormat {
+    /// The whole HTTP body is the address, surrounded by optional whitespace.
+    Plain,
+    /// The HTTP body is a JSON document and the address is the string at a
+    /// dotted path such as `ip` or `data.address`; numeric segments index
+    /// arrays.
+    Json { field: String },
+    /// The DNS name resolves to the caller's address as an A or AAAA record,
+    /// like OpenDNS's `myip.opendns.com`.
+    DnsAddress,
+    /// The DNS name has a TXT record holding the caller's address, like
+    /// Google's `o-o.myaddr.l.google.com`.
+    DnsTxt,
+}
+
+/// An endpoint that reports the caller's address: an `http(s)://` URL, or a
+/// `dns://<resolver>[:port]/<name>` URL naming the resolver to ask and the
+/// record to look up.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "ProviderSpec")]
+pub struct Provider {
+    pub url: String,
+    pub format: ResponseFormat,
+}
+
+impl Provider {
+    /// A provider in the default format for its URL scheme: plain text over
+    /// HTTP, or address records over DNS.
+    pub fn new(url: &str) -> Self {
+        let format = if is_dns_url(url) {
+            ResponseFormat::DnsAddress
+        } else {
+            ResponseFormat::Plain
+        };
+        Provider {
+            url: url.to_string(),
+            format,
+        }
+    }
+
+    fn is_dns(&self) -> bool {
+        matches!(self.format, ResponseFormat::DnsAddress | ResponseFormat::DnsTxt)
+    }
+
+    /// Extracts and validates the address from an HTTP response body.
+    pub fn parse(&self, body: &str) -> Result<IpAddr, String> {
+        let text = match &self.format {
+            ResponseFormat::Json { field } => {
+                let document: serde_json::Value =
+                    serde_json::from_str(body).map_err(|_| format!("not JSON: {}", excerpt(body)))?;
+                let value = field
+                    .split('.')
+                    .try_fold(&document, |value, segment| match segment.parse::<usize>() {
+                        Ok(index) if value.is_array() => value.get(index),
+                        _ => value.get(segment),
+                    })
+                    .ok_or_else(|| format!("no field '{}' in response", field))?;
+                value
+                    .as_str()
+                    .ok_or_else(|| format!("field '{}' is not a string", field))?
+                    .trim()
+                    .to_string()
+            }
+            _ => body.trim().to_string(),
+        };
+
+        text.parse().map_err(|_| format!("not an IP address: {}", excerpt(&text)))
+    }
+}
+
+/// A provider as written in `config.toml`: either a bare URL or a table with
+/// `url`, `format` and, for JSON, `field`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ProviderSpec {
+    Url(String),
+    Table(ProviderTable),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProviderTable {
+    url: String,
+    format: Option<FormatName>,
+    field: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum FormatName {
+    Plain,
+    Json,
+    Address,
+    Txt,
+}
+
+impl TryFrom<ProviderSpec> for Provider {
+    type Error = String;
+
+    fn try_from(spec: ProviderSpec) -> Result<Self, Self::Error> {
+        let table = match spec {
+            ProviderSpec::Url(url) => ProviderTable {
+                url,
+                format: None,
+                field: None,
+            },
+            ProviderSpec::Table(table) => table,
+        };
+
+        let url = table.url;
+        let dns = is_dns_url(&url);
+        if !dns && !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!("provider {}: expected an http://, https:// or dns:// URL", url));
+        }
+        if dns && dns_target(&url).is_none() {
+            return Err(format!("provider {}: expected dns://<resolver>/<name>", url));
+        }
+
+        let format = match (table.format, table.field, dns) {
+            (None, None, _) => return Ok(Provider::new(&url)),
+            (Some(FormatName::Json), Some(field), false) if !field.is_empty() => ResponseFormat::Json { field },
+            (Some(FormatName::Json), None, false) => {
+                return Err(format!("provider {}: format = \"json\" needs a `field`", url))
+            }
+            (_, Some(_), _) => return Err(format!("provider {}: `field` only applies to format = \"json\"", url)),
+            (Some(FormatName::Plain), None, false) => ResponseFormat::Plain,
+            (Some(FormatName::Address), None, true) => ResponseFormat::DnsAddress,
+            (Some(FormatName::Txt), None, true) => ResponseFormat::DnsTxt,
+            (Some(_), None, true) => {
+                return Err(format!("provider {}: DNS providers use format = \"address\" or \"txt\"", url))
+            }
+            (Some(_), None, false) => {
+                return Err(format!("provider {}: HTTP providers use format = \"plain\" or \"json\"", url))
+            }
+        };
+        Ok(Provider { url, format })
+    }
+}
+
+fn is_dns_url(url: &str) -> bool {
+    url.starts_with("dns://")
+}
+
+/// Splits `dns://<resolver>[:port]/<name>` into the resolver and the name.
+fn dns_target(url: &str) -> Option<(&str, &str)> {
+    let (resolver, name) = url.strip_prefix("dns://")?.split_once('/')?;
+    let name = name.trim_end_matches('.');
+    (!resolver.is_empty() && !name.is_empty()).then_some((resolver, name))
+}
+
+/// A public address together with the provider that reported it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PublicAddress {
+    pub address: IpAddr,
+    pub provider: String,
+    /// The proxy the provider was reached through, without credentials.
+    pub proxy: Option<String>,
+    /// Filled in from the GeoIP databases, if any are configured.
+    pub geo: Option<GeoInfo>,
+}
+
+impl PublicAddress {
+    /// The provider's host name, which is all the UI has room for.
+    pub fn provider_host(&self) -> &str {
+        provider_host(&self.provider)
+    }
+
+    /// The provider's host name, followed by the proxy it was reached
+    /// through, if any.
+    pub fn source(&self) -> String {
+        match &self.proxy {
+            Some(proxy) => format!("{} through {}", self.provider_host(), proxy),
+            None => self.provider_host().to_string(),
+        }
+    }
+}
+
+/// A provider that failed to answer or answered with something other than an
+/// address of the requested family.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProviderError {
+    pub provider: String,
+    pub family: IpFamily,
+    pub message: String,
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let family = self.family.label();
+        if self.provider.is_empty() {
+            write!(f, "{} lookup: {}", family, self.message)
+        } else {
+            write!(f, "{} ({}): {}", provider_host(&self.provider), family, self.message)
+        }
+    }
+}
+
+/// Outcome of looking up one address family: the address if any provider
+/// produced a valid one, the errors of every provider that did not, and in
+/// consensus mode the answers that differ from the majority.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PublicLookup {
+    pub address: Option<PublicAddress>,
+    pub errors: Vec<ProviderError>,
+    pub disagreements: Vec<PublicAddress>,
+}
+
+/// Egress addresses as seen from the internet, looked up separately for each
+/// address family so dual-stack hosts report both.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PublicIp {
+    pub v4: Option<PublicAddress>,
+    pub v6: Option<PublicAddress>,
+    pub errors: Vec<ProviderError>,
+    pub disagreements: Vec<PublicAddress>,
+}
+
+impl PublicIp {
+    /// Whether consensus providers answered for `family` without a majority
+    /// for any address, which leaves all their answers as disagreements.
+    pub fn no_majority(&self, family: IpFamily) -> bool {
+        let address = match family {
+            IpFamily::V4 => &self.v4,
+            IpFamily::V6 => &self.v6,
+        };
+        address.is_none() && self.disagreements.iter().any(|answer| family.matches(&answer.address))
+    }
+}
+
+/// The public addresses from the moment they were first observed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PublicIpChange {
+    #[serde(rename = "observed_at", serialize_with = "serialize_unix_time")]
+    pub at: SystemTime,
+    pub v4: Option<IpAddr>,
+    pub v6: Option<IpAddr>,
+}
+
+/// Public addresses seen over time, oldest first, with one entry per change.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct PublicIpHistory {
+    changes: VecDeque<PublicIpChange>,
+}
+
+impl PublicIpHistory {
+    /// Adds an entry if a lookup found an address that differs from the last
+    /// one seen for its family. A family whose lookup failed keeps its last
+    /// known address, so flaky providers do not show up as changes.
+    pub fn record(&mut self, public_ip: &PublicIp, at: SystemTime) -> bool {
+        let last = self.changes.back();
+        let v4 = public_ip.v4.as_ref().map(|public| public.address).or(last.and_then(|last| last.v4));
+        let v6 = public_ip.v6.as_ref().map(|public| public.address).or(last.and_then(|last| last.v6));
+
+        let unchanged = match last {
+            Some(last) => last.v4 == v4 && last.v6 == v6,
+            None => v4.is_none() && v6.is_none(),
+        };
+        if unchanged {
+            return false;
+        }
+
+        if self.changes.len() == HISTORY_LEN {
+            self.changes.pop_front();
+        }
+        self.changes.push_back(PublicIpChange { at, v4, v6 });
+        true
+    }
+
+    pub fn changes(&self) -> impl DoubleEndedIterator<Item = &PublicIpChange> {
+        self.changes.iter()
+    }
+
+    /// When the public address last changed, as opposed to when it was first
+    /// seen after startup.
+    pub fn changed_at(&self) -> Option<SystemTime> {
+        (self.changes.len() > 1).then(|| self.changes.back().map(|change| change.at))?
+    }
+}
+
+fn serialize_unix_time<S: Serializer>(at: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    let seconds = at.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    serializer.serialize_u64(seconds)
+}
+
+/// Asks each provider in turn over a connection bound to `family`, returning
+/// the first answer that parses as an address of that family.
+pub async fn get_real_public_ip(egress: &Egress, providers: &[Provider], family: IpFamily) -> PublicLookup {
+    let mut lookup = PublicLookup::default();
+    let session = match Session::open(egress, family).await {
+        Ok(session) => session,
+        Err(error) => {
+            lookup.errors.push(error);
+            return lookup;
+        }
+    };
+
+    let result = timeout(LOOKUP_TIMEOUT, async {
+        for provider in providers {
+            match session.query(provider).await {
+                Ok(address) => {
+                    lookup.address = Some(session.answer(provider, address));
+                    return;
+                }
+                Err(message) => lookup.errors.push(ProviderError {
+                    provider: provider.url.clone(),
+                    family,
+                    message,
+                }),
+            }
+        }
+    }).await;
+
+    if result.is_err() {
+        lookup.errors.push(ProviderError {
+            provider: String::new(),
+            family,
+            message: "lookup timed out".to_string(),
+        });
+    }
+    lookup
+}
+
+/// Asks the first `count` providers concurrently and reports the address more
+/// than half of the answers agree on. Answers that differ are kept as
+/// disagreements, since split tunnels and load-balanced NAT make different
+/// services see different egress addresses. Without a majority, as in a 1-1
+/// split, no address is reported and every answer is a disagreement.
+pub async fn get_consensus_public_ip(
+    egress: &Egress,
+    providers: &[Provider],
+    family: IpFamily,
+    count: usize,
+) -> PublicLookup {
+    let mut lookup = PublicLookup::default();
+    let session = match Session::open(egress, family).await {
+        Ok(session) => session,
+        Err(error) => {
+            lookup.errors.push(error);
+            return lookup;
+        }
+    };
+
+    let providers = &providers[..count.min(providers.len())];
+    let answers = futures::future::join_all(providers.iter().map(|provider| async {
+        match timeout(LOOKUP_TIMEOUT, session.query(provider)).await {
+            Ok(result) => result,
+            Err(_) => Err("timed out".to_string()),
+        }
+    }))
+    .await;
+
+    let mut tally: Vec<(IpAddr, usize)> = Vec::new();
+    let mut answered = Vec::new();
+    for (provider, answer) in providers.iter().zip(answers) {
+        match answer {
+            Ok(address) => {
+                match tally.iter_mut().find(|(seen, _)| *seen == address) {
+                    Some((_, votes)) => *votes += 1,
+                    None => tally.push((address, 1)),
+                }
+                answered.push(session.answer(provider, address));
+            }
+            Err(message) => lookup.errors.push(ProviderError {
+                provider: provider.url.clone(),
+                family,
+                message,
+            }),
+        }
+    }
+
+    let Some((majority, votes)) = tally.iter().max_by_key(|(_, votes)| *votes).copied() else {
+        return lookup;
+    };
+    if votes * 2 <= answered.len() {
+        lookup.errors.push(ProviderError {
+            provider: String::new(),
+            family,
+            message: format!("no majority among {} answers", answered.len()),
+        });
+        lookup.disagreements = answered;
+        return lookup;
+    }
+    for answer in answered {
+        if answer.address != majority {
+            lookup.disagreements.push(answer);
+        } else if lookup.address.is_none() {
+            lookup.address = Some(answer);
+        }
+    }
+    lookup
+}
+
+/// What the queries of one lookup share: the local address they are sent
+/// from, which pins them to one address family and optionally one interface,
+/// and the HTTP client built on it.
+struct Session<'a> {
+    egress: &'a Egress,
+    family: IpFamily,
+    local_address: IpAddr,
+    client: reqwest::Client,
+}
+
+impl<'a> Session<'a> {
+    /// Works out the source address once for the whole lookup and builds the
+    /// session on it.
+    async fn open(egress: &'a Egress, family: IpFamily) -> Result<Self, ProviderError> {
+        let error = |message| ProviderError {
+            provider: String::new(),
+            family,
+            message,
+        };
+        let local_address = egress.local_address(family).await.map_err(error)?;
+        Session::new(egress, family, local_address).map_err(error)
+    }
+
+    fn new(egress: &'a Egress, family: IpFamily, local_address: IpAddr) -> Result<Self, String> {
+        let client = egress
+            .client(local_address, REQUEST_TIMEOUT)
+            .map_err(|client_error| format!("cannot create HTTP client: {}", client_error))?;
+        Ok(Session {
+            egress,
+            family,
+            local_address,
+            client,
+        })
+    }
+
+    async fn query(&self, provider: &Provider) -> Result<IpAddr, String> {
+        let address = if provider.is_dns() {
+            query_dns(provider, self.family, self.local_address).await?
+        } else {
+            self.query_http(provider).await?
+        };
+        if !self.family.matches(&address) {
+            return Err(format!("answered {} over the wrong address family", address));
+        }
+        Ok(address)
+    }
+
+    async fn query_http(&self, provider: &Provider) -> Result<IpAddr, String> {
+        let response = self.client.get(&provider.url).send().await.map_err(|error| {
+            match self.egress.proxy_label(&provider.url) {
+                Some(proxy) => format!("request via {} failed: {}", proxy, error.without_url()),
+                None => format!("request failed: {}", error.without_url()),
+            }
+        })?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+
+        let body = response
+            .text()
+            .await
+            .map_err(|error| format!("cannot read response: {}", error.without_url()))?;
+        provider.parse(&body)
+    }
+
+    /// The answer `provider` gave, noting the proxy it came through. DNS
+    /// providers are asked directly, as HTTP proxies do not carry UDP.
+    fn answer(&self, provider: &Provider, address: IpAddr) -> PublicAddress {
+        let proxy = if provider.is_dns() {
+            None
+        } else {
+            self.egress.proxy_label(&provider.url)
+        };
+        PublicAddress {
+            address,
+            provider: provider.url.clone(),
+            proxy,
+            geo: None,
+        }
+    }
+}
+
+/// Asks the provider's resolver from `local_address`, so the address it
+/// reports back is our egress address for that family.
+async fn query_dns(provider: &Provider, family: IpFamily, local_address: IpAddr) -> Result<IpAddr, String> {
+    let (resolver, name) = dns_target(&provider.url).ok_or("malformed dns:// URL")?;
+    let resolver = resolve_resolver(resolver, family).await?;
+
+    let record = match (&provider.format, family) {
+        (ResponseFormat::DnsTxt, _) => RecordType::Txt,
+        (_, IpFamily::V4) => RecordType::A,
+        (_, IpFamily::V6) => RecordType::Aaaa,
+    };
+    let records = dns::query_from(local_address, resolver, name, record, REQUEST_TIMEOUT)
+        .await
+        .map_err(|error| format!("{:#}", error))?;
+
+    let mut texts = Vec::new();
+    for record in records {
+        match record {
+            Record::Address(address) => return Ok(address),
+            Record::Txt(text) => match text.trim().parse() {
+                Ok(address) => return Ok(address),
+                Err(_) => texts.push(text),
+            },
+            Record::Name(_) => {}
+        }
+    }
+    match texts.first() {
+        Some(text) => Err(format!("not an IP address: {}", excerpt(text))),
+        None => Err("no matching records".to_string()),
+    }
+}
+
+/// Finds an address of `family` for a resolver given as an IP literal (IPv6
+/// in brackets) or a host name, with an optional port.
+async fn resolve_resolver(resolver: &str, family: IpFamily) -> Result<SocketAddr, String> {
+    let (host, port) = match resolver.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') && (host.starts_with('[') || !host.contains(':')) => {
+            let port = port.parse().map_err(|_| format!("invalid resolver port '{}'", port))?;
+            (host, port)
+        }
+        _ => (resolver, DNS_PORT),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    if let Ok(address) = host.parse::<IpAddr>() {
+        if !family.matches(&address) {
+            return Err(format!("resolver {} is not an {} address", address, family.label()));
+        }
+        return Ok(SocketAddr::new(address, port));
+    }
+    let addresses = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|error| format!("cannot resolve {}: {}", host, error))?;
+    addresses
+        .into_iter()
+        .find(|address| family.matches(&address.ip()))
+        .ok_or_else(|| format!("resolver {} has no {} address", host, family.label()))
+}
+
+fn provider_host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    if rest.starts_with('[') {
+        if let Some(end) = rest.find(']') {
+            return &rest[..=end];
+        }
+    }
+    rest.split(['/', ':']).next().unwrap_or(rest)
+}
+
+/// The start of `text` on a single line, for quoting unexpected responses.
+fn excerpt(text: &str) -> String {
+    let line: String = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.is_empty() {
+        return "empty response".to_string();
+    }
+    match line.char_indices().nth(BODY_EXCERPT_LEN) {
+        Some((end, _)) => format!("{:?}...", &line[..end]),
+        None => format!("{:?}", line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn spec(url: &str, format: Option<FormatName>, field: Option<&str>) -> Result<Provider, String> {
+        Provider::try_from(ProviderSpec::Table(ProviderTable {
+            url: url.to_string(),
+            format,
+            field: field.map(str::to_string),
+        }))
+    }
+
+    fn json(field: &str) -> Provider {
+        Provider {
+            url: "http://json.example/".to_string(),
+            format: ResponseFormat::Json { field: field.to_string() },
+        }
+    }
+
+    /// Answers every request for a path in `pages` with its status and body,
+    /// like a provider would, returning the base URL.
+    async fn stand_in(pages: &'static [(&'static str, u16, &'static str)]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !request.windows(4).any(|end| end == b"\r\n\r\n") {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(len) => request.extend_from_slice(&buf[..len]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request);
+                    let path = request.split_whitespace().nth(1).unwrap_or_default();
+                    let (status, body) = pages
+                        .iter()
+                        .find(|(page, _, _)| *page == path)
+                        .map_or((404, ""), |(_, status, body)| (*status, *body));
+                    let response = format!(
+                        "HTTP/1.1 {} Stand-in\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        base
+    }
+
+    const PAGES: &[(&str, u16, &str)] = &[
+        ("/plain", 200, "203.0.113.7\n"),
+        ("/other", 200, "198.51.100.2"),
+        ("/json", 200, r#"{"data": {"ip": "203.0.113.7"}}"#),
+        ("/portal", 200, "<html><head><title>Hotel Wi-Fi</title></head><body>Log in</body></html>"),
+        ("/down", 503, "maintenance"),
+        ("/v6", 200, "2001:db8::7"),
+    ];
+
+    fn at(base: &str, path: &str) -> Provider {
+        Provider::new(&format!("{}{}", base, path))
+    }
+
+    #[test]
+    fn bare_urls_take_the_scheme_default() {
+        assert_eq!(Provider::new("https://api.ipify.org").format, ResponseFormat::Plain);
+        assert_eq!(Provider::new("dns://1.1.1.1/whoami.example").format, ResponseFormat::DnsAddress);
+        assert_eq!(spec("https://api.ipify.org", None, None), Ok(Provider::new("https://api.ipify.org")));
+    }
+
+    #[test]
+    fn provider_specs_are_validated() {
+        assert_eq!(
+            spec("https://ipinfo.io/json", Some(FormatName::Json), Some("ip")).unwrap().format,
+            ResponseFormat::Json { field: "ip".to_string() }
+        );
+        assert_eq!(
+            spec("dns://ns1.google.com/o-o.myaddr.l.google.com", Some(FormatName::Txt), None).unwrap().format,
+            ResponseFormat::DnsTxt
+        );
+
+        let rejected = [
+            spec("ftp://example.com/ip", None, None),
+            spec("dns://1.1.1.1", None, None),
+            spec("dns:///whoami.example", None, None),
+            spec("https://ipinfo.io/json", Some(FormatName::Json), None),
+            spec("https://ipinfo.io/json", Some(FormatName::Json), Some("")),
+            spec("https://ipinfo.io/ip", Some(FormatName::Plain), Some("ip")),
+            spec("https://ipinfo.io/ip", Some(FormatName::Txt), None),
+            spec("dns://1.1.1.1/whoami.example", Some(FormatName::Json), Some("ip")),
+            spec("dns://1.1.1.1/whoami.example", Some(FormatName::Plain), None),
+        ];
+        for result in rejected {
+            assert!(result.is_err(), "{:?} was accepted", result);
+        }
+        assert_eq!(
+            spec("https://ipinfo.io/json", Some(FormatName::Json), None).unwrap_err(),
+            "provider https://ipinfo.io/json: format = \"json\" needs a `field`"
+        );
+    }
+
+    #[test]
+    fn providers_from_config_toml() {
+        #[derive(Deserialize)]
+        struct File {
+            providers: Vec<Provider>,
+        }
+
+        let file: File = toml::from_str(
+            r#"providers = ["https://icanhazip.com", { url = "https://ipinfo.io/json", format = "json", field = "ip" }]"#,
+        )
+        .unwrap();
+        assert_eq!(file.providers[0], Provider::new("https://icanhazip.com"));
+        assert_eq!(file.providers[1], Provider {
+            url: "https://ipinfo.io/json".to_string(),
+            format: ResponseFormat::Json { field: "ip".to_string() },
+        });
+
+        let unknown = toml::from_str::<File>(r#"providers = [{ url = "https://ipinfo.io/json", path = "ip" }]"#);
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn json_field_paths() {
+        let body = r#"{"ip": " 203.0.113.7 ", "data": {"addresses": [{"v6": "2001:db8::7"}]}, "asn": 64496}"#;
+
+        assert_eq!(json("ip").parse(body), Ok("203.0.113.7".parse().unwrap()));
+        assert_eq!(json("data.addresses.0.v6").parse(body), Ok("2001:db8::7".parse().unwrap()));
+        assert_eq!(json("data.addresses.1.v6").parse(body), Err("no field 'data.addresses.1.v6' in response".to_string()));
+        assert_eq!(json("asn").parse(body), Err("field 'asn' is not a string".to_string()));
+        assert_eq!(json("ip").parse("203.0.113.7"), Err("not JSON: \"203.0.113.7\"".to_string()));
+    }
+
+    #[test]
+    fn plain_bodies_must_be_addresses() {
+        let provider = Provider::new("https://icanhazip.com");
+
+        assert_eq!(provider.parse("\n 2001:db8::1 \r\n"), Ok("2001:db8::1".parse().unwrap()));
+        assert_eq!(provider.parse("  "), Err("not an IP address: empty response".to_string()));
+        let long = provider.parse(&"x".repeat(100)).unwrap_err();
+        assert_eq!(long, format!("not an IP address: {:?}...", "x".repeat(BODY_EXCERPT_LEN)));
+    }
+
+    #[tokio::test]
+    async fn captive_portal_is_a_provider_error() {
+        let base = stand_in(PAGES).await;
+        let providers = [at(&base, "/portal"), at(&base, "/down"), at(&base, "/plain")];
+
+        let lookup = get_real_public_ip(&Egress::default(), &providers, IpFamily::V4).await;
+
+        let address = lookup.address.unwrap();
+        assert_eq!(address.address, "203.0.113.7".parse::<IpAddr>().unwrap());
+        assert_eq!(address.provider, format!("{}/plain", base));
+        let messages: Vec<&str> = lookup.errors.iter().map(|error| error.message.as_str()).collect();
+        assert_eq!(messages, [
+            "not an IP address: \"<html><head><title>Hotel Wi-Fi</title></\"...",
+            "HTTP 503 Service Unavailable",
+        ]);
+        assert_eq!(lookup.errors[0].provider, format!("{}/portal", base));
+    }
+
+    #[tokio::test]
+    async fn json_provider_over_http() {
+        let base = stand_in(PAGES).await;
+        let provider = Provider {
+            url: format!("{}/json", base),
+            format: ResponseFormat::Json { field: "data.ip".to_string() },
+        };
+
+        let lookup = get_real_public_ip(&Egress::default(), &[provider], IpFamily::V4).await;
+
+        assert_eq!(lookup.address.unwrap().address, "203.0.113.7".parse::<IpAddr>().unwrap());
+        assert!(lookup.errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn answers_of_the_wrong_family_are_rejected() {
+        let base = stand_in(PAGES).await;
+
+        let lookup = get_real_public_ip(&Egress::default(), &[at(&base, "/v6")], IpFamily::V4).await;
+
+        assert_eq!(lookup.address, None);
+        assert_eq!(lookup.errors[0].message, "answered 2001:db8::7 over the wrong address family");
+    }
+
+    #[tokio::test]
+    async fn consensus_keeps_disagreements() {
+        let base = stand_in(PAGES).await;
+        let providers = [at(&base, "/other"), at(&base, "/plain"), at(&base, "/portal"), at(&base, "/json"), at(&base, "/plain")];
+
+        let lookup = get_consensus_public_ip(&Egress::default(), &providers, IpFamily::V4, 5).await;
+
+        assert_eq!(lookup.address.unwrap().address, "203.0.113.7".parse::<IpAddr>().unwrap());
+        let disagreements: Vec<IpAddr> = lookup.disagreements.iter().map(|answer| answer.address).collect();
+        assert_eq!(disagreements, ["198.51.100.2".parse::<IpAddr>().unwrap()]);
+        assert_eq!(lookup.errors.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn consensus_ties_have_no_majority() {
+        let base = stand_in(PAGES).await;
+        let providers = [at(&base, "/other"), at(&base, "/plain"), at(&base, "/portal")];
+
+        let lookup = get_consensus_public_ip(&Egress::default(), &providers, IpFamily::V4, 3).await;
+
+        assert_eq!(lookup.address, None);
+        let disagreements: Vec<IpAddr> = lookup.disagreements.iter().map(|answer| answer.address).collect();
+        assert_eq!(disagreements, ["198.51.100.2".parse::<IpAddr>().unwrap(), "203.0.113.7".parse().unwrap()]);
+        let messages: Vec<String> = lookup.errors.iter().map(ToString::to_string).collect();
+        assert_eq!(messages.last().unwrap(), "IPv4 lookup: no majority among 2 answers");
+
+        let public_ip = PublicIp {
+            disagreements: lookup.disagreements,
+            ..PublicIp::default()
+        };
+        assert!(public_ip.no_majority(IpFamily::V4));
+        assert!(!public_ip.no_majority(IpFamily::V6));
+    }
+
+    #[tokio::test]
+    async fn a_lone_answer_is_a_majority() {
+        let base = stand_in(PAGES).await;
+        let providers = [at(&base, "/portal"), at(&base, "/plain")];
+
+        let lookup = get_consensus_public_ip(&Egress::default(), &providers, IpFamily::V4, 2).await;
+
+        assert_eq!(lookup.address.unwrap().address, "203.0.113.7".parse::<IpAddr>().unwrap());
+        assert!(lookup.disagreements.is_empty());
+        assert_eq!(lookup.errors.len(), 1);
+    }
+
+    fn observed(v4: Option<&str>, v6: Option<&str>) -> PublicIp {
+        let public = |address: &str| PublicAddress {
+            address: address.parse().unwrap(),
+            provider: "https://ip.example/".to_string(),
+            proxy: None,
+            geo: None,
+        };
+        PublicIp {
+            v4: v4.map(public),
+            v6: v6.map(public),
+            ..PublicIp::default()
+        }
+    }
+
+    fn time(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    fn addresses(history: &PublicIpHistory) -> Vec<(Option<IpAddr>, Option<IpAddr>)> {
+        history.changes().map(|change| (change.v4, change.v6)).collect()
+    }
+
+    #[test]
+    fn history_only_records_changes() {
+        let mut history = PublicIpHistory::default();
+
+        assert!(!history.record(&observed(None, None), time(10)));
+        assert!(history.record(&observed(Some("203.0.113.7"), None), time(20)));
+        assert!(!history.record(&observed(Some("203.0.113.7"), None), time(30)));
+        // A failed lookup keeps the last known address.
+        assert!(!history.record(&observed(None, None), time(40)));
+        assert_eq!(addresses(&history), [(Some("203.0.113.7".parse().unwrap()), None)]);
+
+        assert!(history.record(&observed(Some("198.51.100.9"), None), time(50)));
+        assert!(history.record(&observed(Some("198.51.100.9"), Some("2001:db8::7")), time(60)));
+        assert!(!history.record(&observed(None, Some("2001:db8::7")), time(70)));
+        assert_eq!(addresses(&history), [
+            (Some("203.0.113.7".parse().unwrap()), None),
+            (Some("198.51.100.9".parse().unwrap()), None),
+            (Some("198.51.100.9".parse().unwrap()), Some("2001:db8::7".parse().unwrap())),
+        ]);
+    }
+
+    #[test]
+    fn changed_at_ignores_the_first_sighting() {
+        let mut history = PublicIpHistory::default();
+        assert_eq!(history.changed_at(), None);
+
+        history.record(&observed(Some("203.0.113.7"), None), time(20));
+        assert_eq!(history.changed_at(), None);
+
+        history.record(&observed(Some("198.51.100.9"), None), time(50));
+        history.record(&observed(Some("198.51.100.9"), None), time(80));
+        assert_eq!(history.changed_at(), Some(time(50)));
+    }
+
+    #[test]
+    fn history_drops_the_oldest_change() {
+        let mut history = PublicIpHistory::default();
+        for i in 0..HISTORY_LEN as u64 + 2 {
+            let address = format!("198.51.100.{}", i);
+            history.record(&observed(Some(&address), None), time(i));
+        }
+
+        assert_eq!(history.changes().count(), HISTORY_LEN);
+        assert_eq!(history.changes().next().unwrap().at, time(2));
+        assert_eq!(history.changed_at(), Some(time(HISTORY_LEN as u64 + 1)));
+    }
+}
@@ -0,0 +1,282 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// Nameservers systemd-resolved listens on in `resolv.conf`, the stub and the
+/// proxy without DNSSEC and LLMNR.
+const RESOLVED_STUBS: [&str; 2] = ["127.0.0.53", "127.0.0.54"];
+
+/// The effective resolver configuration: `/etc/resolv.conf`, what
+/// systemd-resolved forwards to when that points at its stub, per-link
+/// servers from systemd, and `/etc/hosts`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolverConfig {
+    /// Nameservers in the order they are tried, as written, so link-local
+    /// IPv6 servers keep their `%interface` suffix.
+    pub nameservers: Vec<String>,
+    pub search: Vec<String>,
+    pub options: Vec<String>,
+    /// Global upstream servers of systemd-resolved, empty unless
+    /// `resolv.conf` points at its stub.
+    pub upstream: Vec<String>,
+    pub links: Vec<LinkDns>,
+    pub hosts: Vec<HostEntry>,
+}
+
+impl ResolverConfig {
+    pub fn uses_resolved_stub(&self) -> bool {
+        self.nameservers.iter().any(|nameserver| RESOLVED_STUBS.contains(&nameserver.as_str()))
+    }
+
+    /// The servers queries end up at: the upstreams behind the stub when
+    /// known, otherwise the nameservers themselves.
+    pub fn effective_servers(&self) -> &[String] {
+        match self.upstream.is_empty() {
+            true => &self.nameservers,
+            false => &self.upstream,
+        }
+    }
+}
+
+/// DNS settings systemd-networkd or systemd-resolved holds for one link.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkDns {
+    pub index: u32,
+    pub servers: Vec<String>,
+    /// Search domains; routing-only domains keep their leading `~`.
+    pub domains: Vec<String>,
+}
+
+/// One `/etc/hosts` line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostEntry {
+    pub address: IpAddr,
+    pub names: Vec<String>,
+}
+
+/// Reads the resolver configuration below `root`, which is `/` on a live
+/// system. Only `etc/resolv.conf` is required; the systemd state files and
+/// `etc/hosts` are left out when missing.
+pub fn read_resolver_config(root: &Path) -> Result<ResolverConfig> {
+    let path = root.join("etc/resolv.conf");
+    let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut config = parse_resolv_conf(&content);
+
+    if config.uses_resolved_stub() {
+        if let Ok(content) = fs::read_to_string(root.join("run/systemd/resolve/resolv.conf")) {
+            config.upstream = parse_resolv_conf(&content).nameservers;
+        }
+    }
+    config.links = read_link_dns(root);
+    if let Ok(content) = fs::read_to_string(root.join("etc/hosts")) {
+        config.hosts = parse_hosts(&content);
+    }
+    Ok(config)
+}
+
+/// Per-link state files are named after the interface index: networkd's
+/// under `run/systemd/netif/links`, and resolved's, which hold what was set
+/// with `resolvectl`, under `run/systemd/resolve/netif`. Servers and domains
+/// from both are merged.
+fn read_link_dns(root: &Path) -> Vec<LinkDns> {
+    let mut links: Vec<LinkDns> = Vec::new();
+
+    for (dir, servers_key) in [("run/systemd/netif/links", "DNS"), ("run/systemd/resolve/netif", "SERVERS")] {
+        let Ok(entries) = fs::read_dir(root.join(dir)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Some(index) = entry.file_name().to_str().and_then(|name| name.parse().ok()) else {
+                continue;
+            };
+            let Ok(content) = fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            let state = parse_link_state(&content, index, servers_key);
+            match links.iter_mut().find(|link| link.index == index) {
+                Some(link) => {
+                    for server in state.servers {
+                        if !link.servers.contains(&server) {
+                            link.servers.push(server);
+                        }
+                    }
+                    for domain in state.domains {
+                        if !link.domains.contains(&domain) {
+                            link.domains.push(domain);
+                        }
+                    }
+                }
+                None => links.push(state),
+            }
+        }
+    }
+
+    links.retain(|link| !link.servers.is_empty() || !link.domains.is_empty());
+    links.sort_by_key(|link| link.index);
+    links
+}
+
+/// Parses `resolv.conf` the way glibc does: a `#` or `;` in the first column
+/// makes the line a comment, anything after a `nameserver` address is
+/// ignored, and the last `search` or `domain` line wins.
+pub fn parse_resolv_conf(content: &str) -> ResolverConfig {
+    let mut config = ResolverConfig::default();
+
+    for line in content.lines() {
+        if line.starts_with(['#', ';']) {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("nameserver") => config.nameservers.extend(words.next().map(str::to_string)),
+            Some("search") => config.search = words.map(str::to_string).collect(),
+            Some("domain") => config.search = words.next().map(str::to_string).into_iter().collect(),
+            Some("options") => config.options.extend(words.map(str::to_string)),
+            _ => {}
+        }
+    }
+    config
+}
+
+/// Parses a systemd per-link state file of `KEY=value` lines, taking servers
+/// from `servers_key` and search and routing domains from `DOMAINS` and
+/// `ROUTE_DOMAINS`.
+pub fn parse_link_state(content: &str, index: u32, servers_key: &str) -> LinkDns {
+    let mut link = LinkDns {
+        index,
+        ..LinkDns::default()
+    };
+
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let values = value.split_whitespace().map(str::to_string);
+        match key.trim() {
+            key if key == servers_key => link.servers.extend(values),
+            "DOMAINS" => link.domains.extend(values),
+            "ROUTE_DOMAINS" => link.domains.extend(values.map(|domain| format!("~{}", domain.trim_start_matches('~')))),
+            _ => {}
+        }
+    }
+    link
+}
+
+/// Parses `/etc/hosts`, skipping comments and lines whose address does not
+/// parse.
+pub fn parse_hosts(content: &str) -> Vec<HostEntry> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split('#').next()?.split_whitespace();
+            let address = words.next()?.parse().ok()?;
+            let names: Vec<String> = words.map(str::to_string).collect();
+            (!names.is_empty()).then_some(HostEntry { address, names })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> ResolverConfig {
+        read_resolver_config(Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/resolver"))).unwrap()
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn stub_is_replaced_by_its_upstreams() {
+        let config = fixture();
+
+        assert!(config.uses_resolved_stub());
+        assert_eq!(config.nameservers, ["127.0.0.53"]);
+        assert_eq!(config.upstream, ["192.0.2.53", "2001:db8::53", "fe80::1%eth0"]);
+        assert_eq!(config.effective_servers(), config.upstream);
+    }
+
+    #[test]
+    fn search_and_options_from_the_stub_file() {
+        let config = fixture();
+
+        assert_eq!(config.search, ["corp.example"]);
+        assert_eq!(config.options, ["edns0", "trust-ad", "timeout:2"]);
+    }
+
+    #[test]
+    fn link_settings_from_networkd_and_resolved() {
+        assert_eq!(fixture().links, vec![
+            LinkDns {
+                index: 2,
+                servers: strings(&["192.0.2.53", "2001:db8::53", "198.51.100.53"]),
+                domains: strings(&["corp.example", "~internal.example", "~vpn.example"]),
+            },
+            LinkDns {
+                index: 7,
+                servers: strings(&["10.8.0.1"]),
+                domains: strings(&["~."]),
+            },
+        ]);
+    }
+
+    #[test]
+    fn hosts_with_aliases() {
+        let hosts = fixture().hosts;
+        let entry = |address: &str, names: &[&str]| HostEntry {
+            address: address.parse().unwrap(),
+            names: strings(names),
+        };
+
+        assert_eq!(hosts, vec![
+            entry("127.0.0.1", &["localhost"]),
+            entry("127.0.1.1", &["build.corp.example", "build"]),
+            entry("::1", &["localhost", "ip6-localhost", "ip6-loopback"]),
+            entry("ff02::1", &["ip6-allnodes"]),
+            entry("192.0.2.80", &["intranet.corp.example", "intranet", "wiki"]),
+        ]);
+    }
+
+    #[test]
+    fn plain_resolv_conf() {
+        let config = parse_resolv_conf("nameserver 10.0.0.1\nnameserver 10.0.0.2 10.0.0.3\ndomain home.arpa\n");
+
+        assert!(!config.uses_resolved_stub());
+        assert_eq!(config.effective_servers(), ["10.0.0.1", "10.0.0.2"]);
+        assert_eq!(config.search, ["home.arpa"]);
+    }
+
+    #[test]
+    fn last_search_or_domain_line_wins() {
+        assert_eq!(parse_resolv_conf("domain a.example\nsearch b.example c.example\n").search, ["b.example", "c.example"]);
+        assert_eq!(parse_resolv_conf("search b.example c.example\ndomain a.example x.example\n").search, ["a.example"]);
+        assert!(parse_resolv_conf("nameserver 127.0.0.54\n").uses_resolved_stub());
+    }
+
+    #[test]
+    fn comments_and_unknown_keys() {
+        let config = parse_resolv_conf("#nameserver 10.0.0.9\n;search old.example\nsortlist 10.0.0.0/8\nnameserver 10.0.0.1 # primary\n");
+
+        assert_eq!(config.nameservers, ["10.0.0.1"]);
+        assert!(config.search.is_empty());
+        assert!(config.options.is_empty());
+    }
+
+    #[test]
+    fn comment_characters_only_count_in_the_first_column() {
+        let config = parse_resolv_conf("  # indented\nsearch a.example #b.example\noptions rotate;ndots:2\n");
+
+        assert_eq!(config.search, ["a.example", "#b.example"]);
+        assert_eq!(config.options, ["rotate;ndots:2"]);
+    }
+
+    #[test]
+    fn missing_resolv_conf() {
+        let error = read_resolver_config(Path::new("/nonexistent")).unwrap_err();
+
+        assert_eq!(error.to_string(), "Failed to read /nonexistent/etc/resolv.conf");
+    }
+}
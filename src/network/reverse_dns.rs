@@ -0,0 +1,156 @@
+use super::dns::{self, Record, RecordType};
+use super::Collector;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Upper bound on one PTR lookup.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a name found for an address is trusted before asking again.
+const FOUND_TTL: Duration = Duration::from_secs(600);
+/// How long an address without a name waits before asking again, shorter so
+/// a record added while we run shows up reasonably soon.
+const MISSING_TTL: Duration = Duration::from_secs(120);
+/// How long an expired entry nobody asks about again is kept. Shown
+/// addresses are asked about on every tick, well within this.
+const FORGET_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+struct Entry {
+    name: Option<String>,
+    /// `None` while a lookup is in flight.
+    expires: Option<Instant>,
+}
+
+/// PTR names for addresses, filled in by background lookups so callers only
+/// ever read what is already known.
+#[derive(Debug, Default)]
+pub struct ReverseDns {
+    entries: Mutex<HashMap<IpAddr, Entry>>,
+}
+
+impl ReverseDns {
+    /// The cached name for `address`, if any. An address seen for the first
+    /// time, or whose entry has expired, gets a lookup started on its own
+    /// task; the previous name keeps being returned until it finishes.
+    pub fn get(self: &Arc<Self>, collector: &Arc<dyn Collector>, address: IpAddr) -> Option<String> {
+        let (name, stale) = self.cached(address, Instant::now());
+        if stale {
+            let cache = self.clone();
+            let collector = collector.clone();
+            tokio::spawn(async move {
+                let name = collector.reverse_dns(address).await;
+                cache.insert(address, name, Instant::now());
+            });
+        }
+        name
+    }
+
+    /// The cached name for `address` at `now` and whether it needs a lookup,
+    /// which is then taken to be in flight.
+    fn cached(&self, address: IpAddr, now: Instant) -> (Option<String>, bool) {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.entry(address).or_insert(Entry {
+            name: None,
+            expires: Some(now),
+        });
+
+        let stale = entry.expires.is_some_and(|expires| expires <= now);
+        if stale {
+            entry.expires = None;
+        }
+        (entry.name.clone(), stale)
+    }
+
+    /// Records the outcome of a lookup finished at `now`. Entries that expired
+    /// a while ago without being asked for again belong to connections that
+    /// are gone, so they are dropped here rather than kept forever.
+    fn insert(&self, address: IpAddr, name: Option<String>, now: Instant) {
+        let ttl = if name.is_some() { FOUND_TTL } else { MISSING_TTL };
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.expires.map_or(true, |expires| expires + FORGET_AFTER > now));
+        entries.insert(
+            address,
+            Entry {
+                name,
+                expires: Some(now + ttl),
+            },
+        );
+    }
+}
+
+/// Asks `resolver` for the PTR record of `address`. Failures of any kind mean
+/// there is no name to show.
+pub async fn lookup_ptr(resolver: SocketAddr, address: IpAddr) -> Option<String> {
+    let records = dns::query(resolver, &dns::reverse_name(address), RecordType::Ptr, LOOKUP_TIMEOUT)
+        .await
+        .ok()?;
+    records.into_iter().find_map(|record| match record {
+        Record::Name(name) => Some(name),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 10));
+    const OTHER: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(198, 51, 100, 20));
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn first_sight_starts_one_lookup() {
+        let cache = ReverseDns::default();
+        let start = Instant::now();
+
+        assert_eq!(cache.cached(ADDRESS, start), (None, true));
+        assert_eq!(cache.cached(ADDRESS, start + secs(30)), (None, false));
+    }
+
+    #[test]
+    fn found_names_live_longer_than_missing_ones() {
+        let cache = ReverseDns::default();
+        let start = Instant::now();
+        cache.insert(ADDRESS, Some("host.example".to_string()), start);
+        cache.insert(OTHER, None, start);
+
+        assert_eq!(cache.cached(ADDRESS, start + MISSING_TTL), (Some("host.example".to_string()), false));
+        assert_eq!(cache.cached(OTHER, start + MISSING_TTL), (None, true));
+
+        // The old name is shown while it is looked up again.
+        assert_eq!(cache.cached(ADDRESS, start + FOUND_TTL), (Some("host.example".to_string()), true));
+        assert_eq!(cache.cached(ADDRESS, start + FOUND_TTL + secs(1)), (Some("host.example".to_string()), false));
+    }
+
+    #[test]
+    fn forgotten_entries_are_pruned() {
+        let cache = ReverseDns::default();
+        let start = Instant::now();
+        cache.insert(ADDRESS, None, start);
+        cache.insert(OTHER, Some("host.example".to_string()), start);
+
+        let later = start + MISSING_TTL + FORGET_AFTER;
+        cache.insert(IpAddr::V4(std::net::Ipv4Addr::LOCALHOST), None, later);
+
+        let entries = cache.entries.lock().unwrap();
+        assert!(!entries.contains_key(&ADDRESS));
+        assert!(entries.contains_key(&OTHER));
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[test]
+    fn lookups_in_flight_are_kept() {
+        let cache = ReverseDns::default();
+        let start = Instant::now();
+        cache.cached(ADDRESS, start);
+
+        cache.insert(OTHER, None, start + FOUND_TTL * 10);
+
+        assert_eq!(cache.cached(ADDRESS, start + FOUND_TTL * 10), (None, false));
+    }
+}
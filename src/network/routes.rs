@@ -0,0 +1,158 @@
+use super::IpFamily;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+
+/// Routing table ids the kernel reserves, named as `ip route` names them.
+const RESERVED_TABLES: [(u32, &str); 3] = [(253, "default"), (254, "main"), (255, "local")];
+
+/// One entry of a kernel routing table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub family: IpFamily,
+    pub destination: IpAddr,
+    pub prefix_len: u8,
+    /// Next hop, `None` for directly connected networks.
+    pub gateway: Option<IpAddr>,
+    /// Outgoing interface, `None` for blackhole and similar routes.
+    pub interface: Option<String>,
+    pub metric: u32,
+    /// Table name from `rt_tables`, or its id when it has none.
+    pub table: String,
+    /// Route type as `ip route` names it, e.g. `unicast`, `unreachable`.
+    pub kind: &'static str,
+    /// Source address preferred for traffic using the route.
+    pub source: Option<IpAddr>,
+}
+
+impl Route {
+    pub fn is_default(&self) -> bool {
+        self.prefix_len == 0
+    }
+
+    pub fn in_main_table(&self) -> bool {
+        self.table == "main"
+    }
+}
+
+/// The destination as `ip route` shows it: `default`, or `address/prefix`,
+/// preceded by the type for anything but a unicast route.
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.kind != "unicast" {
+            write!(f, "{} ", self.kind)?;
+        }
+        match self.is_default() {
+            true => write!(f, "default"),
+            false => write!(f, "{}/{}", self.destination, self.prefix_len),
+        }
+    }
+}
+
+/// A policy routing rule, which picks the table a packet is routed by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub family: IpFamily,
+    pub priority: u32,
+    /// What the rule matches as `ip rule` shows it, e.g. `from all fwmark 0xca6c`.
+    pub selector: String,
+    /// What happens to matching packets, e.g. `lookup main`, `unreachable`.
+    pub action: String,
+}
+
+/// Default routes leaving through `interface`, from every table.
+pub fn default_routes<'a>(routes: &'a [Route], interface: &'a str) -> impl Iterator<Item = &'a Route> {
+    routes
+        .iter()
+        .filter(move |route| route.is_default() && route.interface.as_deref() == Some(interface))
+}
+
+/// Names routing table `id` after `names`, read from `rt_tables`.
+pub fn table_name(id: u32, names: &HashMap<u32, String>) -> String {
+    names
+        .get(&id)
+        .cloned()
+        .or_else(|| RESERVED_TABLES.iter().find(|(reserved, _)| *reserved == id).map(|(_, name)| name.to_string()))
+        .unwrap_or_else(|| id.to_string())
+}
+
+/// Parses an iproute2 `rt_tables` file: one `id name` pair per line, `#`
+/// starting a comment.
+pub fn parse_rt_tables(content: &str) -> HashMap<u32, String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split('#').next()?.split_whitespace();
+            let id = words.next()?.parse().ok()?;
+            Some((id, words.next()?.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(destination: &str, prefix_len: u8, interface: Option<&str>, table: &str) -> Route {
+        let destination: IpAddr = destination.parse().unwrap();
+        Route {
+            family: if destination.is_ipv4() { IpFamily::V4 } else { IpFamily::V6 },
+            destination,
+            prefix_len,
+            gateway: None,
+            interface: interface.map(str::to_string),
+            metric: 0,
+            table: table.to_string(),
+            kind: "unicast",
+            source: None,
+        }
+    }
+
+    #[test]
+    fn default_routes_from_every_table() {
+        let routes = [
+            route("0.0.0.0", 0, Some("eth0"), "main"),
+            route("192.0.2.0", 24, Some("eth0"), "main"),
+            route("0.0.0.0", 0, Some("wg0"), "wg"),
+            route("::", 0, Some("eth0"), "isp2"),
+            Route {
+                kind: "unreachable",
+                ..route("0.0.0.0", 0, None, "vpn")
+            },
+        ];
+
+        let tables: Vec<&str> = default_routes(&routes, "eth0").map(|route| route.table.as_str()).collect();
+        assert_eq!(tables, ["main", "isp2"]);
+        assert_eq!(default_routes(&routes, "wg0").count(), 1);
+        assert_eq!(default_routes(&routes, "eth1").count(), 0);
+    }
+
+    #[test]
+    fn destinations_as_ip_route_shows_them() {
+        assert_eq!(route("0.0.0.0", 0, Some("eth0"), "main").to_string(), "default");
+        assert_eq!(route("2001:db8::", 64, Some("eth0"), "main").to_string(), "2001:db8::/64");
+        let unreachable = Route {
+            kind: "unreachable",
+            ..route("10.0.0.0", 8, None, "main")
+        };
+        assert_eq!(unreachable.to_string(), "unreachable 10.0.0.0/8");
+    }
+
+    #[test]
+    fn rt_tables_with_comments() {
+        let names = parse_rt_tables("# reserved\n255\tlocal\n\n100 isp2   # backup uplink\n#1 inr.ruhep\n0x10 hex\n200\n");
+
+        assert_eq!(names, HashMap::from([(255, "local".to_string()), (100, "isp2".to_string())]));
+    }
+
+    #[test]
+    fn table_names_fall_back_to_reserved_names_and_ids() {
+        let names = HashMap::from([(100, "isp2".to_string()), (254, "primary".to_string())]);
+
+        assert_eq!(table_name(100, &names), "isp2");
+        assert_eq!(table_name(254, &names), "primary");
+        assert_eq!(table_name(253, &names), "default");
+        assert_eq!(table_name(255, &HashMap::new()), "local");
+        assert_eq!(table_name(51820, &names), "51820");
+    }
+}
@@ -0,0 +1,141 @@
+use super::collector::Collector;
+use super::geoip::GeoInfo;
+use super::neighbors::Neighbor;
+use super::public_ip::{PublicAddress, PublicIp, PublicLookup};
+use super::resolver::ResolverConfig;
+use super::routes::{Route, Rule};
+use super::sockets::Connection;
+use super::stun::StunReport;
+use super::{Interface, IpFamily, LinkStats};
+use anyhow::Result;
+use futures::future::{self, BoxFuture, FutureExt};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+/// Replays canned links, counter sequences, a public IP and a STUN report so
+/// the data model and UI can be driven deterministically without a real host
+/// or network.
+/// Each `link_stats` call advances every counter sequence by one step and
+/// repeats the last step once a sequence runs out.
+#[derive(Debug)]
+pub struct ScriptedCollector {
+    links: Vec<Interface>,
+    counters: Mutex<HashMap<String, VecDeque<(u64, u64)>>>,
+    public_ip: PublicIp,
+    stun: StunReport,
+}
+
+impl ScriptedCollector {
+    pub fn new(links: Vec<Interface>) -> Self {
+        ScriptedCollector {
+            links,
+            counters: Mutex::new(HashMap::new()),
+            public_ip: PublicIp::default(),
+            stun: StunReport::default(),
+        }
+    }
+
+    pub fn with_counters(self, name: &str, samples: impl IntoIterator<Item = (u64, u64)>) -> Self {
+        self.counters
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), samples.into_iter().collect());
+        self
+    }
+
+    pub fn with_public_ip(mut self, address: IpAddr) -> Self {
+        let public = Some(PublicAddress {
+            address,
+            provider: "scripted".to_string(),
+            proxy: None,
+            geo: None,
+        });
+        match address {
+            IpAddr::V4(_) => self.public_ip.v4 = public,
+            IpAddr::V6(_) => self.public_ip.v6 = public,
+        }
+        self
+    }
+
+    pub fn with_stun(mut self, report: StunReport) -> Self {
+        self.stun = report;
+        self
+    }
+}
+
+impl Collector for ScriptedCollector {
+    fn links(&self) -> Result<Vec<Interface>> {
+        Ok(self.links.clone())
+    }
+
+    fn link_stats(&self) -> Result<HashMap<String, LinkStats>> {
+        let mut counters = self.counters.lock().unwrap();
+
+        Ok(counters
+            .iter_mut()
+            .filter_map(|(name, samples)| {
+                let (rx, tx) = if samples.len() > 1 {
+                    samples.pop_front()?
+                } else {
+                    *samples.front()?
+                };
+                let stats = LinkStats {
+                    received_bytes: rx,
+                    transmitted_bytes: tx,
+                    ..LinkStats::default()
+                };
+                Some((name.clone(), stats))
+            })
+            .collect())
+    }
+
+    fn routes(&self) -> Result<Vec<Route>> {
+        Ok(Vec::new())
+    }
+
+    fn rules(&self) -> Result<Vec<Rule>> {
+        Ok(Vec::new())
+    }
+
+    fn resolver(&self) -> Result<ResolverConfig> {
+        Ok(ResolverConfig::default())
+    }
+
+    fn connections(&self) -> Result<Vec<Connection>> {
+        Ok(Vec::new())
+    }
+
+    fn neighbors(&self) -> Result<Vec<Neighbor>> {
+        Ok(Vec::new())
+    }
+
+    fn public_ip(&self, family: IpFamily) -> BoxFuture<'_, PublicLookup> {
+        let address = match family {
+            IpFamily::V4 => self.public_ip.v4.clone(),
+            IpFamily::V6 => self.public_ip.v6.clone(),
+        };
+        let lookup = PublicLookup {
+            address,
+            errors: Vec::new(),
+            disagreements: Vec::new(),
+        };
+        future::ready(lookup).boxed()
+    }
+
+    fn interface_public_ip<'a>(&'a self, _interface: &'a str, _family: IpFamily) -> BoxFuture<'a, PublicLookup> {
+        future::ready(PublicLookup::default()).boxed()
+    }
+
+    fn stun(&self) -> BoxFuture<'_, StunReport> {
+        future::ready(self.stun.clone()).boxed()
+    }
+
+    fn geo(&self, _address: IpAddr) -> Option<GeoInfo> {
+        None
+    }
+
+    fn reverse_dns(&self, _address: IpAddr) -> BoxFuture<'_, Option<String>> {
+        future::ready(None).boxed()
+    }
+}
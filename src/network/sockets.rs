@@ -0,0 +1,34 @@
+use std::net::SocketAddr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+impl Protocol {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+        }
+    }
+}
+
+/// An open TCP or UDP socket, listening or connected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connection {
+    pub protocol: Protocol,
+    pub local: SocketAddr,
+    /// The peer, `None` for listening and unconnected sockets.
+    pub remote: Option<SocketAddr>,
+    /// State as `ss` names it, e.g. `ESTAB`, `LISTEN`, `UNCONN`.
+    pub state: &'static str,
+    pub uid: u32,
+}
+
+impl Connection {
+    pub fn is_listening(&self) -> bool {
+        self.remote.is_none()
+    }
+}
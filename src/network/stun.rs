@@ -0,0 +1,446 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+pub const DEFAULT_STUN_SERVERS: &[&str] = &["stun.l.google.com:19302", "stun.cloudflare.com:3478"];
+
+const STUN_PORT: u16 = 3478;
+const MAGIC_COOKIE: u32 = 0x2112_a442;
+const HEADER_LEN: usize = 20;
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS: u16 = 0x0101;
+const BINDING_ERROR: u16 = 0x0111;
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTR_ERROR_CODE: u16 = 0x0009;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+/// Retransmission timeouts after each send, doubling as RFC 5389 suggests
+/// but capped well below its 39.5 seconds so the UI is not held up.
+const RETRANSMIT: [Duration; 3] = [
+    Duration::from_millis(500),
+    Duration::from_millis(1000),
+    Duration::from_millis(2000),
+];
+
+/// How the NAT in front of us maps our UDP socket, judged by asking two STUN
+/// servers at different addresses from the same local port.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NatMapping {
+    /// Fewer than two servers answered, so there was nothing to compare.
+    #[default]
+    Unknown,
+    /// The mapped address is our own local address.
+    NoNat,
+    /// Both servers saw the same public address and port, so peers can reach
+    /// us on the address any one of them reports.
+    EndpointIndependent,
+    /// Each server saw a different public port or address, which defeats
+    /// most UDP hole punching.
+    AddressPortDependent,
+}
+
+impl NatMapping {
+    pub fn label(&self) -> &'static str {
+        match self {
+            NatMapping::Unknown => "unknown",
+            NatMapping::NoNat => "no NAT",
+            NatMapping::EndpointIndependent => "endpoint-independent",
+            NatMapping::AddressPortDependent => "address/port-dependent",
+        }
+    }
+}
+
+/// The public transport address a STUN server saw for our UDP socket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StunMapping {
+    pub server: String,
+    pub mapped_address: SocketAddr,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StunReport {
+    /// The address the socket was sent from, as chosen by the routing table.
+    pub local_address: Option<SocketAddr>,
+    pub mappings: Vec<StunMapping>,
+    pub mapping: NatMapping,
+    pub errors: Vec<String>,
+}
+
+impl StunReport {
+    /// The address peers should use, which is the first server's answer.
+    pub fn mapped_address(&self) -> Option<SocketAddr> {
+        self.mappings.first().map(|mapping| mapping.mapped_address)
+    }
+}
+
+/// Sends Binding requests to the first two servers from one IPv4 socket and
+/// classifies the NAT mapping by comparing what each saw.
+pub async fn probe(servers: &[String]) -> StunReport {
+    let mut report = StunReport::default();
+
+    let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await {
+        Ok(socket) => socket,
+        Err(error) => {
+            report.errors.push(format!("cannot open UDP socket: {}", error));
+            return report;
+        }
+    };
+
+    let mut server_addresses: Vec<SocketAddr> = Vec::new();
+    for server in servers.iter().take(2) {
+        let result = match resolve(server).await {
+            Ok(address) if server_addresses.iter().any(|seen| seen.ip() == address.ip()) => {
+                Err(anyhow!("shares an address with another server"))
+            }
+            Ok(address) => {
+                server_addresses.push(address);
+                binding(&socket, address).await
+            }
+            Err(error) => Err(error),
+        };
+
+        match result {
+            Ok(mapped_address) => report.mappings.push(StunMapping {
+                server: server.clone(),
+                mapped_address,
+            }),
+            Err(error) => report.errors.push(format!("{}: {:#}", server, error)),
+        }
+    }
+
+    if let (Some(&server), Ok(port)) = (server_addresses.first(), socket.local_addr().map(|local| local.port())) {
+        report.local_address = source_address(server).await.map(|ip| SocketAddr::new(ip, port));
+    }
+    report.mapping = classify(report.local_address, &report.mappings);
+    report
+}
+
+fn classify(local_address: Option<SocketAddr>, mappings: &[StunMapping]) -> NatMapping {
+    let Some(first) = mappings.first() else {
+        return NatMapping::Unknown;
+    };
+    if Some(first.mapped_address) == local_address {
+        return NatMapping::NoNat;
+    }
+    match mappings.get(1) {
+        Some(second) if second.mapped_address == first.mapped_address => NatMapping::EndpointIndependent,
+        Some(_) => NatMapping::AddressPortDependent,
+        None => NatMapping::Unknown,
+    }
+}
+
+/// The local address the kernel would send from to reach `server`; connecting
+/// a UDP socket picks a route without sending anything.
+async fn source_address(server: SocketAddr) -> Option<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await.ok()?;
+    socket.connect(server).await.ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+/// Resolves `host[:port]` to an IPv4 address, defaulting to the STUN port.
+async fn resolve(server: &str) -> Result<SocketAddr> {
+    let (host, port) = split_host_port(server)?;
+    tokio::net::lookup_host((host, port))
+        .await
+        .with_context(|| format!("cannot resolve {}", host))?
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| anyhow!("{} has no IPv4 address", host))
+}
+
+/// Splits `host[:port]`. An IPv6 literal takes a port only in brackets, as
+/// in `[2001:db8::1]:3478`; a bare one is all host.
+fn split_host_port(server: &str) -> Result<(&str, u16)> {
+    let parse_port = |port: &str| port.parse().map_err(|_| anyhow!("invalid port '{}'", port));
+    if let Some(rest) = server.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']').ok_or_else(|| anyhow!("missing ']' in '{}'", server))?;
+        return match rest {
+            "" => Ok((host, STUN_PORT)),
+            _ => match rest.strip_prefix(':') {
+                Some(port) => Ok((host, parse_port(port)?)),
+                None => bail!("unexpected '{}' after ']'", rest),
+            },
+        };
+    }
+    match server.split_once(':') {
+        Some((host, port)) if !port.contains(':') => Ok((host, parse_port(port)?)),
+        _ => Ok((server, STUN_PORT)),
+    }
+}
+
+async fn binding(socket: &UdpSocket, server: SocketAddr) -> Result<SocketAddr> {
+    let transaction_id = transaction_id();
+    let request = build_request(&transaction_id);
+    let mut buf = [0; 1024];
+
+    for wait in RETRANSMIT {
+        socket.send_to(&request, server).await.context("cannot send request")?;
+
+        let answer = timeout(wait, async {
+            loop {
+                let (len, from) = socket.recv_from(&mut buf).await?;
+                // Late answers to an earlier server or transaction are ignored.
+                if from == server && buf[..len].get(8..HEADER_LEN) == Some(&transaction_id[..]) {
+                    return Ok::<_, std::io::Error>(len);
+                }
+            }
+        })
+        .await;
+
+        match answer {
+            Ok(Ok(len)) => return parse_response(&buf[..len], &transaction_id),
+            Ok(Err(error)) => bail!("cannot read response: {}", error),
+            Err(_) => continue,
+        }
+    }
+    bail!("no answer")
+}
+
+fn transaction_id() -> [u8; 12] {
+    let mut id = [0; 12];
+    let high = RandomState::new().build_hasher().finish().to_be_bytes();
+    let low = RandomState::new().build_hasher().finish().to_be_bytes();
+    id[..8].copy_from_slice(&high);
+    id[8..].copy_from_slice(&low[..4]);
+    id
+}
+
+pub fn build_request(transaction_id: &[u8; 12]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(HEADER_LEN);
+    packet.extend_from_slice(&BINDING_REQUEST.to_be_bytes());
+    packet.extend_from_slice(&0u16.to_be_bytes());
+    packet.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+    packet.extend_from_slice(transaction_id);
+    packet
+}
+
+/// Reads the mapped address from a Binding response, preferring
+/// XOR-MAPPED-ADDRESS over the legacy MAPPED-ADDRESS.
+pub fn parse_response(packet: &[u8], transaction_id: &[u8; 12]) -> Result<SocketAddr> {
+    if packet.len() < HEADER_LEN {
+        bail!("response too short");
+    }
+    let kind = u16::from_be_bytes([packet[0], packet[1]]);
+    let len = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+    if packet[4..8] != MAGIC_COOKIE.to_be_bytes() {
+        bail!("not a STUN response");
+    }
+    if packet[8..HEADER_LEN] != transaction_id[..] {
+        bail!("response to another transaction");
+    }
+    let attributes = packet
+        .get(HEADER_LEN..HEADER_LEN + len)
+        .ok_or_else(|| anyhow!("response ends early"))?;
+
+    let mut mapped = None;
+    let mut xor_mapped = None;
+    let mut error = None;
+    let mut pos = 0;
+    while pos + 4 <= attributes.len() {
+        let attr = u16::from_be_bytes([attributes[pos], attributes[pos + 1]]);
+        let attr_len = u16::from_be_bytes([attributes[pos + 2], attributes[pos + 3]]) as usize;
+        let value = attributes
+            .get(pos + 4..pos + 4 + attr_len)
+            .ok_or_else(|| anyhow!("attribute ends early"))?;
+        match attr {
+            ATTR_XOR_MAPPED_ADDRESS => xor_mapped = Some(parse_address(value, Some(transaction_id))?),
+            ATTR_MAPPED_ADDRESS => mapped = Some(parse_address(value, None)?),
+            ATTR_ERROR_CODE if value.len() >= 4 => {
+                let code = u16::from(value[2] & 0x07) * 100 + u16::from(value[3]);
+                let reason = String::from_utf8_lossy(&value[4..]).trim().to_string();
+                error = Some(format!("error {} {}", code, reason));
+            }
+            _ => {}
+        }
+        pos += 4 + (attr_len + 3) / 4 * 4;
+    }
+
+    match kind {
+        BINDING_SUCCESS => xor_mapped.or(mapped).ok_or_else(|| anyhow!("no mapped address in response")),
+        BINDING_ERROR => bail!(error.unwrap_or_else(|| "error response".to_string())),
+        _ => bail!("unexpected message type {:#06x}", kind),
+    }
+}
+
+/// Decodes a (XOR-)MAPPED-ADDRESS value; XOR addresses are masked with the
+/// magic cookie, followed by the transaction id for IPv6.
+fn parse_address(value: &[u8], transaction_id: Option<&[u8; 12]>) -> Result<SocketAddr> {
+    if value.len() < 4 {
+        bail!("malformed address attribute");
+    }
+    let mut mask = [0; 16];
+    if let Some(transaction_id) = transaction_id {
+        mask[..4].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        mask[4..].copy_from_slice(transaction_id);
+    }
+
+    let port = u16::from_be_bytes([value[2] ^ mask[0], value[3] ^ mask[1]]);
+    let ip = match (value[1], &value[4..]) {
+        (0x01, octets) if octets.len() == 4 => {
+            let mut ip = [0; 4];
+            for (i, byte) in ip.iter_mut().enumerate() {
+                *byte = octets[i] ^ mask[i];
+            }
+            IpAddr::V4(Ipv4Addr::from(ip))
+        }
+        (0x02, octets) if octets.len() == 16 => {
+            let mut ip = [0; 16];
+            for (i, byte) in ip.iter_mut().enumerate() {
+                *byte = octets[i] ^ mask[i];
+            }
+            IpAddr::V6(Ipv6Addr::from(ip))
+        }
+        _ => bail!("malformed address attribute"),
+    };
+    Ok(SocketAddr::new(ip, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The transaction id of the sample responses in RFC 5769.
+    const TRANSACTION_ID: [u8; 12] = [0xb7, 0xe7, 0xa7, 0x01, 0xbc, 0x34, 0xd6, 0x86, 0xfa, 0x87, 0xdf, 0xae];
+    const SOFTWARE: u16 = 0x8022;
+
+    fn message(kind: u16, transaction_id: &[u8; 12], attributes: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (attr, value) in attributes {
+            body.extend_from_slice(&attr.to_be_bytes());
+            body.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            body.extend_from_slice(value);
+            body.resize((body.len() + 3) / 4 * 4, 0);
+        }
+        let mut packet = kind.to_be_bytes().to_vec();
+        packet.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        packet.extend_from_slice(transaction_id);
+        packet.extend_from_slice(&body);
+        packet
+    }
+
+    fn mapping(server: &str, mapped_address: &str) -> StunMapping {
+        StunMapping {
+            server: server.to_string(),
+            mapped_address: mapped_address.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn request_bytes() {
+        let request = build_request(&TRANSACTION_ID);
+
+        assert_eq!(request[..8], [0x00, 0x01, 0x00, 0x00, 0x21, 0x12, 0xa4, 0x42]);
+        assert_eq!(request[8..], TRANSACTION_ID);
+    }
+
+    #[test]
+    fn xor_mapped_ipv4() {
+        let packet = message(BINDING_SUCCESS, &TRANSACTION_ID, &[
+            (SOFTWARE, b"test vector"),
+            (ATTR_XOR_MAPPED_ADDRESS, &[0x00, 0x01, 0xa1, 0x47, 0xe1, 0x12, 0xa6, 0x43]),
+        ]);
+
+        assert_eq!(parse_response(&packet, &TRANSACTION_ID).unwrap(), "192.0.2.1:32853".parse().unwrap());
+    }
+
+    #[test]
+    fn xor_mapped_ipv6() {
+        let value = [
+            0x00, 0x02, 0xa1, 0x47, 0x01, 0x13, 0xa9, 0xfa, 0xa5, 0xd3, 0xf1, 0x79,
+            0xbc, 0x25, 0xf4, 0xb5, 0xbe, 0xd2, 0xb9, 0xd9,
+        ];
+        let packet = message(BINDING_SUCCESS, &TRANSACTION_ID, &[(ATTR_XOR_MAPPED_ADDRESS, &value)]);
+
+        assert_eq!(
+            parse_response(&packet, &TRANSACTION_ID).unwrap(),
+            "[2001:db8:1234:5678:11:2233:4455:6677]:32853".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn mapped_address_is_the_fallback() {
+        let mapped: &[u8] = &[0x00, 0x01, 0x80, 0x55, 198, 51, 100, 9];
+        let xor_mapped: &[u8] = &[0x00, 0x01, 0xa1, 0x47, 0xe1, 0x12, 0xa6, 0x43];
+
+        let legacy = message(BINDING_SUCCESS, &TRANSACTION_ID, &[(ATTR_MAPPED_ADDRESS, mapped)]);
+        assert_eq!(parse_response(&legacy, &TRANSACTION_ID).unwrap(), "198.51.100.9:32853".parse().unwrap());
+
+        let both = message(BINDING_SUCCESS, &TRANSACTION_ID, &[(ATTR_MAPPED_ADDRESS, mapped), (ATTR_XOR_MAPPED_ADDRESS, xor_mapped)]);
+        assert_eq!(parse_response(&both, &TRANSACTION_ID).unwrap(), "192.0.2.1:32853".parse().unwrap());
+    }
+
+    #[test]
+    fn transaction_id_mismatch() {
+        let mut other = TRANSACTION_ID;
+        other[11] ^= 1;
+        let packet = message(BINDING_SUCCESS, &other, &[(ATTR_MAPPED_ADDRESS, &[0x00, 0x01, 0x80, 0x55, 198, 51, 100, 9])]);
+
+        assert_eq!(parse_response(&packet, &TRANSACTION_ID).unwrap_err().to_string(), "response to another transaction");
+    }
+
+    #[test]
+    fn malformed_responses() {
+        let error = |packet: &[u8]| parse_response(packet, &TRANSACTION_ID).unwrap_err().to_string();
+
+        let empty = message(BINDING_SUCCESS, &TRANSACTION_ID, &[]);
+        assert_eq!(error(&empty), "no mapped address in response");
+        assert_eq!(error(&empty[..HEADER_LEN - 1]), "response too short");
+
+        let mut long = message(BINDING_SUCCESS, &TRANSACTION_ID, &[(ATTR_MAPPED_ADDRESS, &[0x00, 0x01, 0x80, 0x55, 198, 51, 100, 9])]);
+        long.truncate(long.len() - 2);
+        assert_eq!(error(&long), "response ends early");
+
+        let mut cookie = empty.clone();
+        cookie[4] = 0;
+        assert_eq!(error(&cookie), "not a STUN response");
+
+        let short_address = message(BINDING_SUCCESS, &TRANSACTION_ID, &[(ATTR_XOR_MAPPED_ADDRESS, &[0x00, 0x01, 0xa1, 0x47, 0xe1])]);
+        assert_eq!(error(&short_address), "malformed address attribute");
+    }
+
+    #[test]
+    fn error_response() {
+        let value: &[u8] = &[0x00, 0x00, 0x04, 20, b'U', b'n', b'k', b'n', b'o', b'w', b'n'];
+        let packet = message(BINDING_ERROR, &TRANSACTION_ID, &[(ATTR_ERROR_CODE, value)]);
+
+        assert_eq!(parse_response(&packet, &TRANSACTION_ID).unwrap_err().to_string(), "error 420 Unknown");
+    }
+
+    #[test]
+    fn nat_classification() {
+        let local: SocketAddr = "192.0.2.10:40000".parse().unwrap();
+        let first = mapping("a", "203.0.113.7:61000");
+
+        assert_eq!(classify(Some(local), &[]), NatMapping::Unknown);
+        assert_eq!(classify(Some(local), std::slice::from_ref(&first)), NatMapping::Unknown);
+        assert_eq!(classify(Some(local), &[mapping("a", "192.0.2.10:40000")]), NatMapping::NoNat);
+        assert_eq!(classify(None, &[first.clone(), mapping("b", "203.0.113.7:61000")]), NatMapping::EndpointIndependent);
+        assert_eq!(classify(Some(local), &[first.clone(), mapping("b", "203.0.113.7:61001")]), NatMapping::AddressPortDependent);
+        assert_eq!(classify(Some(local), &[first, mapping("b", "198.51.100.7:61000")]), NatMapping::AddressPortDependent);
+    }
+
+    #[test]
+    fn server_addresses() {
+        assert_eq!(split_host_port("stun.example").unwrap(), ("stun.example", STUN_PORT));
+        assert_eq!(split_host_port("stun.example:19302").unwrap(), ("stun.example", 19302));
+        assert_eq!(split_host_port("192.0.2.1:3479").unwrap(), ("192.0.2.1", 3479));
+        assert_eq!(split_host_port("2001:db8::1").unwrap(), ("2001:db8::1", STUN_PORT));
+        assert_eq!(split_host_port("[2001:db8::1]").unwrap(), ("2001:db8::1", STUN_PORT));
+        assert_eq!(split_host_port("[2001:db8::1]:3479").unwrap(), ("2001:db8::1", 3479));
+        assert!(split_host_port("stun.example:port").is_err());
+        assert!(split_host_port("[2001:db8::1").is_err());
+        assert!(split_host_port("[2001:db8::1]3479").is_err());
+    }
+
+    #[tokio::test]
+    async fn ipv6_servers_are_reported_not_misparsed() {
+        let error = resolve("[::1]:3478").await.unwrap_err();
+
+        assert_eq!(error.to_string(), "::1 has no IPv4 address");
+    }
+}
@@ -0,0 +1,375 @@
+use crate::config::Config;
+use crate::network::{
+    get_network_info, is_loopback, lookup_public_ip, update_network_info, AddressScope, Collector, Interface, InterfaceAddress,
+    IpFamily, NetworkInfo,
+};
+use crate::ui::{format_bytes, geo_summary};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+/// Version of the `--json` document. Bumped whenever a field is renamed,
+/// removed or changes meaning; new fields may appear without a bump.
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    info: &'a NetworkInfo,
+}
+
+/// Collects once, waits one refresh interval and collects again so rates are
+/// included, then prints the result as JSON without touching the terminal.
+pub async fn print_json(collector: &dyn Collector, config: &Config) -> Result<()> {
+    let mut info = get_network_info(collector, config).await.context("Failed to get network info")?;
+    tokio::time::sleep(config.interval).await;
+    update_network_info(&mut info, collector).await.context("Failed to update network info")?;
+
+    let report = JsonReport {
+        schema_version: SCHEMA_VERSION,
+        info: &info,
+    };
+
+    let mut stdout = io::stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, &report)?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+/// Prints the public IP on its own line, IPv4 before IPv6 unless a family is
+/// requested, failing when no provider answered.
+pub async fn print_public(collector: &dyn Collector, family: Option<IpFamily>) -> Result<ExitCode> {
+    let (address, errors, disagreements) = match family {
+        Some(family) => {
+            let lookup = collector.public_ip(family).await;
+            (lookup.address, lookup.errors, lookup.disagreements)
+        }
+        None => {
+            let public_ip = lookup_public_ip(collector).await;
+            (public_ip.v4.or(public_ip.v6), public_ip.errors, public_ip.disagreements)
+        }
+    };
+
+    match address {
+        Some(public) => {
+            println!("{}", public.address);
+            for answer in disagreements.iter().filter(|answer| answer.address.is_ipv4() == public.address.is_ipv4()) {
+                eprintln!("warning: {} reported {}", answer.provider_host(), answer.address);
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        None => {
+            eprintln!("Could not determine the public IP address");
+            for error in errors {
+                eprintln!("  {}", error);
+            }
+            for answer in disagreements {
+                eprintln!("  {} reported {}", answer.provider_host(), answer.address);
+            }
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Prints the most useful local address: IPv4 before IPv6 unless a family is
+/// requested, and routable, settled addresses before link-local, deprecated
+/// or tentative ones.
+pub fn print_local(interfaces: &[Interface], config: &Config, iface: Option<&str>, family: Option<IpFamily>) -> ExitCode {
+    let candidates: Vec<&Interface> = match iface {
+        Some(name) => {
+            let Some(interface) = interfaces.iter().find(|interface| interface.name == name) else {
+                eprintln!("No interface named '{}'", name);
+                return ExitCode::FAILURE;
+            };
+            vec![interface]
+        }
+        None => interfaces
+            .iter()
+            .filter(|interface| interface.status && config.shows_interface(&interface.name, is_loopback(&interface.name)))
+            .collect(),
+    };
+
+    let families = match family {
+        Some(family) => vec![family],
+        None => vec![IpFamily::V4, IpFamily::V6],
+    };
+
+    for family in families {
+        let best = candidates
+            .iter()
+            .flat_map(|interface| match family {
+                IpFamily::V4 => &interface.ipv4_addresses,
+                IpFamily::V6 => &interface.ipv6_addresses,
+            })
+            .min_by_key(|address| address_rank(address));
+
+        if let Some(address) = best {
+            println!("{}", address.address);
+            return ExitCode::SUCCESS;
+        }
+    }
+
+    eprintln!("Could not determine a local IP address");
+    ExitCode::FAILURE
+}
+
+fn address_rank(address: &InterfaceAddress) -> u8 {
+    match address.scope {
+        AddressScope::Loopback => 3,
+        AddressScope::LinkLocal => 2,
+        _ if address.flags.deprecated || address.flags.tentative => 1,
+        _ => 0,
+    }
+}
+
+/// Prints a plain-text table of interfaces for terminals and logs where the
+/// full-screen UI is unwanted.
+pub async fn print_plain(collector: &dyn Collector, config: &Config) -> Result<()> {
+    let info = get_network_info(collector, config).await.context("Failed to get network info")?;
+
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "Hostname:    {}", info.hostname)?;
+    if config.public_ip {
+        for (label, family, public) in [("IPv4", IpFamily::V4, &info.public_ip.v4), ("IPv6", IpFamily::V6, &info.public_ip.v6)] {
+            match public {
+                Some(public) => {
+                    write!(stdout, "Public {}: {} (via {})", label, public.address, public.source())?;
+                    match &public.geo {
+                        Some(geo) => writeln!(stdout, " {}", geo_summary(geo))?,
+                        None => writeln!(stdout)?,
+                    }
+                }
+                None if info.public_ip.no_majority(family) => writeln!(stdout, "Public {}: no majority", label)?,
+                None => writeln!(stdout, "Public {}: unknown", label)?,
+            }
+        }
+        for answer in &info.public_ip.disagreements {
+            writeln!(stdout, "Disagreement: {} (via {})", answer.address, answer.provider_host())?;
+        }
+        if let Some(stun) = &info.stun {
+            match stun.mapped_address() {
+                Some(address) => writeln!(stdout, "STUN:        {} (NAT {})", address, stun.mapping.label())?,
+                None => writeln!(stdout, "STUN:        unknown")?,
+            }
+        }
+    }
+    writeln!(stdout)?;
+
+    let mut rows = vec![[
+        "INTERFACE".to_string(),
+        "STATE".to_string(),
+        "MAC".to_string(),
+        "MTU".to_string(),
+        "RX".to_string(),
+        "TX".to_string(),
+        "ADDRESSES".to_string(),
+    ]];
+    for interface in &info.interfaces {
+        let mut addresses = interface
+            .ipv4_addresses
+            .iter()
+            .chain(&interface.ipv6_addresses)
+            .map(|address| address.to_string());
+
+        rows.push([
+            config.display_name(&interface.name),
+            if interface.status { "up" } else { "down" }.to_string(),
+            interface.mac_address.clone().unwrap_or_else(|| "-".to_string()),
+            interface.mtu.map_or_else(|| "-".to_string(), |mtu| mtu.to_string()),
+            format_bytes(interface.received_bytes, config.units),
+            format_bytes(interface.transmitted_bytes, config.units),
+            addresses.next().unwrap_or_else(|| "-".to_string()),
+        ]);
+        for address in addresses {
+            let mut row: [String; 7] = Default::default();
+            row[6] = address;
+            rows.push(row);
+        }
+    }
+
+    let mut widths = [0; 7];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in &rows {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(stdout, "{}", line.trim_end())?;
+    }
+
+    let egress: Vec<_> = info
+        .interfaces
+        .iter()
+        .filter_map(|interface| Some((interface, interface.public_ip.as_ref()?)))
+        .collect();
+    if !egress.is_empty() {
+        writeln!(stdout)?;
+        for (interface, public_ip) in egress {
+            let answers: Vec<String> = [("IPv4", &public_ip.v4), ("IPv6", &public_ip.v6)]
+                .into_iter()
+                .filter_map(|(label, public)| {
+                    let public = public.as_ref()?;
+                    Some(format!("{} {} (via {})", label, public.address, public.source()))
+                })
+                .collect();
+            let answers = match answers.is_empty() {
+                true => "unknown".to_string(),
+                false => answers.join(", "),
+            };
+            writeln!(stdout, "Egress {}: {}", config.display_name(&interface.name), answers)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::scripted::ScriptedCollector;
+    use crate::network::AddressFlags;
+    use std::net::IpAddr;
+
+    fn address(address: &str, flags: AddressFlags) -> InterfaceAddress {
+        let address: IpAddr = address.parse().unwrap();
+        InterfaceAddress {
+            address,
+            prefix_len: if address.is_ipv4() { 24 } else { 64 },
+            scope: AddressScope::of(&address),
+            flags,
+        }
+    }
+
+    fn interface(name: &str, up: bool, addresses: &[&str]) -> Interface {
+        let mut interface = Interface::new(name, 2);
+        interface.status = up;
+        for &text in addresses {
+            let address = address(text, AddressFlags::default());
+            match address.address {
+                IpAddr::V4(_) => interface.ipv4_addresses.push(address),
+                IpAddr::V6(_) => interface.ipv6_addresses.push(address),
+            }
+        }
+        interface
+    }
+
+    #[test]
+    fn settled_routable_addresses_rank_first() {
+        let deprecated = AddressFlags {
+            deprecated: true,
+            ..AddressFlags::default()
+        };
+        let tentative = AddressFlags {
+            tentative: true,
+            ..AddressFlags::default()
+        };
+
+        assert_eq!(address_rank(&address("2001:db8::1", AddressFlags::default())), 0);
+        assert_eq!(address_rank(&address("fd00::1", AddressFlags::default())), 0);
+        assert_eq!(address_rank(&address("192.168.1.2", AddressFlags::default())), 0);
+        assert_eq!(address_rank(&address("2001:db8::2", deprecated)), 1);
+        assert_eq!(address_rank(&address("2001:db8::3", tentative)), 1);
+        assert_eq!(address_rank(&address("fe80::1", AddressFlags::default())), 2);
+        assert_eq!(address_rank(&address("169.254.0.1", AddressFlags::default())), 2);
+        assert_eq!(address_rank(&address("::1", AddressFlags::default())), 3);
+    }
+
+    #[test]
+    fn local_address_exit_codes() {
+        let config = Config::default();
+        let interfaces = [
+            interface("lo", true, &["127.0.0.1", "::1"]),
+            interface("eth0", true, &["fe80::1"]),
+            interface("eth1", false, &["192.0.2.10"]),
+        ];
+
+        assert_eq!(print_local(&interfaces, &config, None, None), ExitCode::SUCCESS);
+        assert_eq!(print_local(&interfaces, &config, None, Some(IpFamily::V6)), ExitCode::SUCCESS);
+        // eth1 is down and loopback is hidden, which leaves no IPv4 address.
+        assert_eq!(print_local(&interfaces, &config, None, Some(IpFamily::V4)), ExitCode::FAILURE);
+        // A named interface is used even when down.
+        assert_eq!(print_local(&interfaces, &config, Some("eth1"), Some(IpFamily::V4)), ExitCode::SUCCESS);
+        assert_eq!(print_local(&interfaces, &config, Some("wlan0"), None), ExitCode::FAILURE);
+        assert_eq!(print_local(&[], &config, None, None), ExitCode::FAILURE);
+    }
+
+    #[tokio::test]
+    async fn json_report_schema() {
+        let eth0 = interface("eth0", true, &["192.0.2.10", "2001:db8::10"]);
+        let collector = ScriptedCollector::new(vec![eth0])
+            .with_counters("eth0", [(1000, 2000)])
+            .with_public_ip("203.0.113.7".parse().unwrap());
+        let info = get_network_info(&collector, &Config::default()).await.unwrap();
+
+        let report = JsonReport {
+            schema_version: SCHEMA_VERSION,
+            info: &info,
+        };
+        let json = serde_json::to_value(&report).unwrap();
+        let keys = |value: &serde_json::Value| -> Vec<String> { value.as_object().unwrap().keys().cloned().collect() };
+
+        assert_eq!(
+            keys(&json),
+            ["hostname", "interfaces", "public_ip", "public_ip_history", "schema_version", "stun"]
+        );
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["public_ip"]["v4"]["address"], "203.0.113.7");
+        assert_eq!(json["public_ip_history"][0]["v4"], "203.0.113.7");
+
+        let interface = &json["interfaces"][0];
+        assert_eq!(
+            keys(interface),
+            [
+                "driver",
+                "index",
+                "ipv4_addresses",
+                "ipv6_addresses",
+                "link_flags",
+                "mac_address",
+                "mtu",
+                "name",
+                "packets",
+                "public_ip",
+                "rates",
+                "received_bytes",
+                "speed_mbps",
+                "transmitted_bytes",
+                "up",
+            ]
+        );
+        assert_eq!(interface["name"], "eth0");
+        assert_eq!(interface["up"], true);
+        assert_eq!(interface["received_bytes"], 1000);
+        assert_eq!(interface["transmitted_bytes"], 2000);
+        assert_eq!(keys(&interface["rates"]), ["rx_bytes_per_sec", "tx_bytes_per_sec"]);
+        assert_eq!(
+            interface["ipv4_addresses"][0],
+            serde_json::json!({
+                "address": "192.0.2.10",
+                "prefix_len": 24,
+                "scope": "global",
+                "flags": { "temporary": false, "deprecated": false, "tentative": false },
+            })
+        );
+        assert_eq!(interface["ipv6_addresses"][0]["address"], "2001:db8::10");
+    }
+
+    #[tokio::test]
+    async fn public_address_exit_codes() {
+        let collector = ScriptedCollector::new(Vec::new()).with_public_ip("203.0.113.7".parse().unwrap());
+
+        assert_eq!(print_public(&collector, None).await.unwrap(), ExitCode::SUCCESS);
+        assert_eq!(print_public(&collector, Some(IpFamily::V4)).await.unwrap(), ExitCode::SUCCESS);
+        assert_eq!(print_public(&collector, Some(IpFamily::V6)).await.unwrap(), ExitCode::FAILURE);
+
+        let collector = ScriptedCollector::new(Vec::new());
+        assert_eq!(print_public(&collector, None).await.unwrap(), ExitCode::FAILURE);
+    }
+}
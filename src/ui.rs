@@ -1,31 +1,80 @@
-use crate::network::NetworkInfo;
+mod connections;
+mod dns;
+mod interfaces;
+mod neighbors;
+mod routes;
+
+use crate::app::{App, Pane, View};
+use crate::config::{Config, Theme, Units};
+use crate::network::{GeoInfo, NetworkInfo};
+use std::time::{Duration, SystemTime};
 use ratatui::{
     backend::Backend,
+    buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap},
+    widgets::{Block, Borders, Paragraph, Row, Table, Tabs, Widget, Wrap},
     Frame,
 };
 
-pub fn render_ui<B: Backend>(f: &mut Frame<B>, network_info: &NetworkInfo) {
+pub fn render_ui<B: Backend>(f: &mut Frame<B>, network_info: &NetworkInfo, config: &Config, app: &mut App) {
+    app.sync(network_info);
+
+    let debug_height = if config.panels.debug { 7 } else { 0 };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3),
+            Constraint::Length(1),
             Constraint::Min(0),
-            Constraint::Length(7),
+            Constraint::Length(debug_height),
             Constraint::Length(3),
         ])
         .split(f.size());
 
-    render_header(f, chunks[0], network_info);
+    let header = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[0]);
+    render_header(f, header[0], network_info);
+    dns::render_dns_summary(f, header[1], network_info);
+    render_tabs(f, chunks[1], app);
+    
+    match app.view {
+        View::Interfaces if app.detail => interfaces::render_interface_detail(f, chunks[2], network_info, config, app),
+        View::Interfaces => interfaces::render_interfaces(f, chunks[2], network_info, config, app),
+        View::Routes => routes::render_routes(f, chunks[2], network_info, config, app),
+        View::Dns => dns::render_dns(f, chunks[2], network_info, config, app),
+        View::Connections => connections::render_connections(f, chunks[2], network_info, app),
+        View::Neighbors => neighbors::render_neighbors(f, chunks[2], network_info, config, app),
+    }
     
-    render_interfaces(f, chunks[1], network_info);
+    if config.panels.debug {
+        render_debug_info(f, chunks[3], network_info, app);
+    }
     
-    render_debug_info(f, chunks[2], network_info);
+    render_footer(f, chunks[4], app);
     
-    render_footer(f, chunks[3]);
+    if config.theme == Theme::Mono {
+        f.render_widget(Monochrome, f.size());
+    }
+}
+
+/// Strips colors from everything drawn so far, keeping modifiers such as bold
+/// so headings still stand out.
+struct Monochrome;
+
+impl Widget for Monochrome {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buf.get_mut(x, y);
+                cell.fg = Color::Reset;
+                cell.bg = Color::Reset;
+            }
+        }
+    }
 }
 
 fn render_header<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &NetworkInfo) {
@@ -51,257 +100,114 @@ fn render_header<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &Networ
     f.render_widget(header, area);
 }
 
-fn render_interfaces<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &NetworkInfo) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Min(0),
-        ])
-        .split(area);
-    
-    render_public_ip(f, chunks[0], network_info);
-    
-    let interface_count = network_info.interfaces.len();
-    if interface_count == 0 {
-        return;
-    }
-    
-    let constraints: Vec<Rect> = if interface_count <= 2 {
-        Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(vec![Constraint::Ratio(1, interface_count as u32); interface_count])
-            .split(chunks[1])
-            .iter()
-            .copied()
-            .collect()
-    } else {
-        let rows = interface_count.div_ceil(2);
-        let interfaces_area = chunks[1];
-        
-        let row_constraints = vec![Constraint::Ratio(1, rows as u32); rows];
-        let rows_layout: Vec<Rect> = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(row_constraints)
-            .split(interfaces_area)
-            .iter()
-            .copied()
-            .collect();
-        
-        let mut interface_areas = Vec::new();
-        for row in rows_layout {
-            let cols = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
-                .split(row);
-            interface_areas.extend_from_slice(&cols);
-        }
-        
-        interface_areas
-    };
-    
-    for (i, interface) in network_info.interfaces.iter().enumerate() {
-        if i < constraints.len() {
-            render_interface(f, constraints[i], interface);
-        }
-    }
-}
+fn render_tabs<B: Backend>(f: &mut Frame<B>, area: Rect, app: &App) {
+    let titles = View::ALL
+        .iter()
+        .zip(1..)
+        .map(|(view, key)| Spans::from(format!("{} {}", key, view.title())))
+        .collect();
+    let selected = View::ALL.iter().position(|view| *view == app.view).unwrap_or(0);
 
-fn render_public_ip<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &NetworkInfo) {
-    let (public_ip, ip_color) = match &network_info.public_ip {
-        Some(ip) => (ip.as_str(), Color::Green),
-        None => ("Unknown", Color::Red),
-    };
-    
-    let text = vec![Spans::from(vec![
-        Span::styled(
-            "Public IP: ",
-            Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
-        ),
-        Span::styled(
-            public_ip,
-            Style::default().fg(ip_color).add_modifier(Modifier::BOLD),
-        ),
-    ])];
-    
-    let paragraph = Paragraph::new(text)
-        .block(Block::default()
-            .borders(Borders::ALL)
-            .title(" External IP ")
-            .border_style(Style::default().fg(Color::Magenta)))
-        .style(Style::default().fg(Color::White))
-        .alignment(ratatui::layout::Alignment::Center);
-    
-    f.render_widget(paragraph, area);
+    let tabs = Tabs::new(titles)
+        .select(selected)
+        .style(Style::default().fg(Color::Gray))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD | Modifier::REVERSED));
+
+    f.render_widget(tabs, area);
 }
 
-fn render_interface<B: Backend>(f: &mut Frame<B>, area: Rect, interface: &crate::network::Interface) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Min(8),
-            Constraint::Length(7),
-        ])
-        .split(area);
-    
-    render_interface_info(f, chunks[0], interface);
-    
-    render_network_graph(f, chunks[1], interface);
+/// Contents of the table filling a view other than Interfaces.
+struct ViewTable<'a> {
+    title: String,
+    header: Row<'a>,
+    rows: Vec<Row<'a>>,
+    widths: &'a [Constraint],
+    /// Shown instead of the table when it has no rows.
+    empty: &'static str,
 }
 
-fn render_interface_info<B: Backend>(f: &mut Frame<B>, area: Rect, interface: &crate::network::Interface) {
-    let mut rows = Vec::new();
-    
-    let status_color = if interface.status { Color::Green } else { Color::Red };
-    let status_text = if interface.status { "up" } else { "down" };
-    rows.push(Row::new(vec![
-        Cell::from("Status").style(Style::default().fg(Color::Cyan)),
-        Cell::from(status_text).style(Style::default().fg(status_color).add_modifier(Modifier::BOLD)),
-    ]));
-    
-    if let Some(mac) = &interface.mac_address {
-        rows.push(Row::new(vec![
-            Cell::from("MAC Address").style(Style::default().fg(Color::Cyan)),
-            Cell::from(mac.as_str()).style(Style::default().fg(Color::Yellow)),
-        ]));
-    }
-    
-    if let Some(mtu) = &interface.mtu {
-        rows.push(Row::new(vec![
-            Cell::from("MTU").style(Style::default().fg(Color::Cyan)),
-            Cell::from(mtu.to_string()).style(Style::default().fg(Color::White)),
-        ]));
-    }
-    
-    if let Some(speed) = &interface.speed {
-        rows.push(Row::new(vec![
-            Cell::from("Speed").style(Style::default().fg(Color::Cyan)),
-            Cell::from(format!("{} Mbps", speed)).style(Style::default().fg(Color::White)),
-        ]));
-    }
-    
-    if interface.received_bytes > 0 {
-        rows.push(Row::new(vec![
-            Cell::from("RX Bytes").style(Style::default().fg(Color::Cyan)),
-            Cell::from(format_bytes(interface.received_bytes)).style(Style::default().fg(Color::Magenta)),
-        ]));
-    }
-    
-    if interface.transmitted_bytes > 0 {
-        rows.push(Row::new(vec![
-            Cell::from("TX Bytes").style(Style::default().fg(Color::Cyan)),
-            Cell::from(format_bytes(interface.transmitted_bytes)).style(Style::default().fg(Color::Magenta)),
-        ]));
-    }
-    
-    for (i, addr) in interface.ipv4_addresses.iter().enumerate() {
-        let prefix = if i == 0 { "IPv4 Address" } else { "" };
-        rows.push(Row::new(vec![
-            Cell::from(prefix).style(Style::default().fg(Color::Cyan)),
-            Cell::from(addr.as_str()).style(Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
-        ]));
-    }
-    
-    for (i, addr) in interface.ipv6_addresses.iter().enumerate() {
-        let prefix = if i == 0 { "IPv6 Address" } else { "" };
-        rows.push(Row::new(vec![
-            Cell::from(prefix).style(Style::default().fg(Color::Cyan)),
-            Cell::from(addr.as_str()).style(Style::default().fg(Color::Blue)),
-        ]));
+/// Draws a view's table, scrolled to the view's offset, which is clamped so
+/// the last row never leaves the bottom edge.
+fn render_table<B: Backend>(f: &mut Frame<B>, area: Rect, app: &mut App, table: ViewTable) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(table.title)
+        .border_style(pane_style(app, Pane::Table, Color::Cyan));
+
+    if table.rows.is_empty() {
+        let paragraph = Paragraph::new(Span::styled(table.empty, Style::default().fg(Color::DarkGray)))
+            .block(block)
+            .alignment(ratatui::layout::Alignment::Center);
+        f.render_widget(paragraph, area);
+        return;
     }
-    
-    let table = Table::new(rows)
-        .header(Row::new(vec![
-            Cell::from("Property").style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-            Cell::from("Value").style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
-        ]))
-        .block(Block::default()
-            .borders(Borders::ALL)
-            .title(format!(" {} ", interface.name))
-            .border_style(Style::default().fg(Color::Cyan)))
-        .widths(&[
-            Constraint::Percentage(30),
-            Constraint::Percentage(70),
-        ])
-        .column_spacing(1)
-        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+
+    // Borders and the header row.
+    let visible = area.height.saturating_sub(3) as usize;
+    app.set_page_size(Pane::Table, visible);
+    let view = app.view as usize;
+    let scroll = &mut app.table_scrolls[view];
+    *scroll = (*scroll).min(table.rows.len().saturating_sub(visible));
+
+    let widget = Table::new(table.rows.into_iter().skip(*scroll))
+        .header(table.header.style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
+        .block(block)
+        .widths(table.widths)
+        .column_spacing(2)
         .style(Style::default().fg(Color::White));
-    
-    f.render_widget(table, area);
-}
 
-fn render_network_graph<B: Backend>(f: &mut Frame<B>, area: Rect, interface: &crate::network::Interface) {
-    use ratatui::widgets::{Dataset, Chart, Axis};
-    use ratatui::symbols;
-    
-    let rx_data: Vec<(f64, f64)> = interface.usage.rx_history
-        .iter()
-        .enumerate()
-        .map(|(i, &v)| (i as f64, v))
-        .collect();
-    
-    let tx_data: Vec<(f64, f64)> = interface.usage.tx_history
-        .iter()
-        .enumerate()
-        .map(|(i, &v)| (i as f64, v))
-        .collect();
+    f.render_widget(widget, area);
+}
 
-    let max_y = f64::max(interface.usage.max_rx, interface.usage.max_tx) * 1.2;
-    
-    let (y_max, y_label) = format_rate_for_axis(max_y);
-    
-    let datasets = vec![
-        Dataset::default()
-            .name("RX")
-            .marker(symbols::Marker::Braille)
-            .style(Style::default().fg(Color::Green))
-            .data(&rx_data),
-        Dataset::default()
-            .name("TX")
-            .marker(symbols::Marker::Braille)
-            .style(Style::default().fg(Color::Red))
-            .data(&tx_data),
-    ];
-    
-    let chart = Chart::new(datasets)
-        .block(Block::default()
-            .title(format!(" Network Traffic ({}) ", y_label))
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Cyan)))
-        .x_axis(Axis::default()
-            .title("Time")
-            .style(Style::default().fg(Color::Gray))
-            .bounds([0.0, interface.usage.rx_history.len() as f64 - 1.0])
-            .labels(vec![]))
-        .y_axis(Axis::default()
-            .title("Rate")
-            .style(Style::default().fg(Color::Gray))
-            .bounds([0.0, y_max])
-            .labels(vec![
-                "0".into(),
-                format!("{:.1}", y_max / 4.0).into(),
-                format!("{:.1}", y_max / 2.0).into(),
-                format!("{:.1}", y_max * 3.0 / 4.0).into(),
-                format!("{:.1}", y_max).into(),
-            ]));
-    
-    f.render_widget(chart, area);
+/// Border style of a pane, highlighted while it has keyboard focus.
+fn pane_style(app: &App, pane: Pane, color: Color) -> Style {
+    match app.pane == pane {
+        true => Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        false => Style::default().fg(color),
+    }
 }
 
-fn format_rate_for_axis(bytes_per_sec: f64) -> (f64, String) {
-    const KB: f64 = 1024.0;
-    const MB: f64 = KB * 1024.0;
-    
-    if bytes_per_sec < KB {
-        (bytes_per_sec, "B/s".to_string())
-    } else if bytes_per_sec < MB {
-        (bytes_per_sec / KB, "KB/s".to_string())
-    } else {
-        (bytes_per_sec / MB, "MB/s".to_string())
+/// Picks the unit for showing `bytes_per_sec`, returning the divisor to apply
+/// to every value and the unit's label.
+fn rate_unit(bytes_per_sec: f64, units: Units) -> (f64, &'static str) {
+    match units {
+        Units::Binary => {
+            const KB: f64 = 1024.0;
+            const MB: f64 = KB * 1024.0;
+            
+            if bytes_per_sec < KB {
+                (1.0, "B/s")
+            } else if bytes_per_sec < MB {
+                (KB, "KB/s")
+            } else {
+                (MB, "MB/s")
+            }
+        }
+        Units::Decimal => {
+            if bytes_per_sec < 1e3 {
+                (1.0, "B/s")
+            } else if bytes_per_sec < 1e6 {
+                (1e3, "kB/s")
+            } else {
+                (1e6, "MB/s")
+            }
+        }
+        Units::Bits => {
+            let bits_per_sec = bytes_per_sec * 8.0;
+            if bits_per_sec < 1e3 {
+                (0.125, "bit/s")
+            } else if bits_per_sec < 1e6 {
+                (125.0, "kbit/s")
+            } else if bits_per_sec < 1e9 {
+                (125e3, "Mbit/s")
+            } else {
+                (125e6, "Gbit/s")
+            }
+        }
     }
 }
 
-fn render_debug_info<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &NetworkInfo) {
+fn render_debug_info<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &NetworkInfo, app: &mut App) {
     let mut text = Vec::new();
     
     text.push(Spans::from(vec![
@@ -320,13 +226,13 @@ fn render_debug_info<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &Ne
         ]));
     }
     
-    if let Some(sysinfo_names) = network_info.debug_info.get("sysinfo") {
-        let joined = sysinfo_names.iter()
+    if let Some(counter_names) = network_info.debug_info.get("counters") {
+        let joined = counter_names.iter()
             .map(|s| s.to_string())
             .collect::<Vec<_>>()
             .join(", ");
         text.push(Spans::from(vec![
-            Span::styled("System Info: ", Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD)),
+            Span::styled("Counters: ", Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD)),
             Span::styled(joined, Style::default().fg(Color::Cyan)),
         ]));
     }
@@ -344,23 +250,60 @@ fn render_debug_info<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &Ne
         }
     }
     
+    if let Some(errors) = network_info.debug_info.get("errors") {
+        for error in errors {
+            text.push(Spans::from(vec![
+                Span::styled("Error: ", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+                Span::styled(error.as_str(), Style::default().fg(Color::White)),
+            ]));
+        }
+    }
+    
+    let history: Vec<String> = network_info.public_ip_history.changes().rev()
+        .map(|change| {
+            let addresses: Vec<String> = change.v4.iter().chain(&change.v6).map(|address| address.to_string()).collect();
+            let age = SystemTime::now().duration_since(change.at).unwrap_or_default();
+            format!("{} ({})", addresses.join(" "), format_age(age))
+        })
+        .collect();
+    if !history.is_empty() {
+        text.push(Spans::from(vec![
+            Span::styled("Public IP History: ", Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD)),
+            Span::styled(history.join(", "), Style::default().fg(Color::White)),
+        ]));
+    }
+    
+    for error in &network_info.public_ip.errors {
+        text.push(Spans::from(vec![
+            Span::styled("Provider: ", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::styled(error.to_string(), Style::default().fg(Color::White)),
+        ]));
+    }
+    
+    let visible = area.height.saturating_sub(2) as usize;
+    app.set_page_size(Pane::Debug, visible);
+    app.debug_scroll = app.debug_scroll.min(text.len().saturating_sub(visible));
+    
     let paragraph = Paragraph::new(text)
         .block(Block::default()
             .borders(Borders::ALL)
             .title("Debug Info")
-            .border_style(Style::default().fg(Color::Magenta)))
+            .border_style(pane_style(app, Pane::Debug, Color::Magenta)))
         .style(Style::default().fg(Color::White))
-        .wrap(Wrap { trim: true });
+        .wrap(Wrap { trim: true })
+        .scroll((app.debug_scroll as u16, 0));
     
     f.render_widget(paragraph, area);
 }
 
-fn render_footer<B: Backend>(f: &mut Frame<B>, area: Rect) {
+fn render_footer<B: Backend>(f: &mut Frame<B>, area: Rect, app: &App) {
+    let keys = match (app.view, app.detail) {
+        (View::Interfaces, true) => "q: exit   ESC/Enter: back to list   ↑↓/j/k: scroll addresses   PgUp/PgDn: page   1-5: views",
+        (View::Interfaces, false) => "q/ESC: exit   ↑↓/j/k: select or scroll   Enter: details   Tab: switch pane   1-5: views",
+        _ => "q/ESC: exit   ↑↓/j/k: scroll   PgUp/PgDn: page   Tab: switch pane   1-5: views",
+    };
     let text = vec![Spans::from(vec![
-        Span::styled(
-            "Press 'q' or ESC to exit",
-            Style::default().fg(Color::White),
-        ),
+        Span::styled(keys, Style::default().fg(Color::White)),
     ])];
     
     let paragraph = Paragraph::new(text)
@@ -371,20 +314,167 @@ fn render_footer<B: Backend>(f: &mut Frame<B>, area: Rect) {
     f.render_widget(paragraph, area);
 }
 
-fn format_bytes(bytes: u64) -> String {
-    const KB: f64 = 1024.0;
-    const MB: f64 = KB * 1024.0;
-    const GB: f64 = MB * 1024.0;
+/// Network owner and location on one line, e.g.
+/// `AS13335 Cloudflare, Inc. · Amsterdam, Netherlands (NL)`.
+pub fn geo_summary(geo: &GeoInfo) -> String {
+    let network = match (geo.asn, &geo.organization) {
+        (Some(asn), Some(organization)) => Some(format!("AS{} {}", asn, organization)),
+        (Some(asn), None) => Some(format!("AS{}", asn)),
+        (None, organization) => organization.clone(),
+    };
+    let country = match (&geo.country, &geo.country_code) {
+        (Some(country), Some(code)) => Some(format!("{} ({})", country, code)),
+        (country, code) => country.clone().or_else(|| code.clone()),
+    };
+    let place = match (&geo.city, country) {
+        (Some(city), Some(country)) => Some(format!("{}, {}", city, country)),
+        (city, country) => city.clone().or(country),
+    };
+
+    network.into_iter().chain(place).collect::<Vec<_>>().join(" · ")
+}
+
+/// How long ago something happened, in the largest whole unit.
+pub fn format_age(age: Duration) -> String {
+    let plural = |count: u64, unit: &str| match count {
+        1 => format!("1 {} ago", unit),
+        _ => format!("{} {}s ago", count, unit),
+    };
+    match age.as_secs() {
+        0..=59 => "just now".to_string(),
+        seconds @ 60..=3599 => plural(seconds / 60, "minute"),
+        seconds @ 3600..=86399 => plural(seconds / 3600, "hour"),
+        seconds => plural(seconds / 86400, "day"),
+    }
+}
+
+pub fn format_bytes(bytes: u64, units: Units) -> String {
+    let (base, prefixes) = match units {
+        Units::Binary => (1024.0, ["KB", "MB", "GB"]),
+        Units::Decimal | Units::Bits => (1000.0, ["kB", "MB", "GB"]),
+    };
+    let kb = base;
+    let mb = kb * base;
+    let gb = mb * base;
     
     let bytes_f64 = bytes as f64;
     
-    if bytes_f64 < KB {
+    if bytes_f64 < kb {
         format!("{} B", bytes)
-    } else if bytes_f64 < MB {
-        format!("{:.2} KB", bytes_f64 / KB)
-    } else if bytes_f64 < GB {
-        format!("{:.2} MB", bytes_f64 / MB)
+    } else if bytes_f64 < mb {
+        format!("{:.2} {}", bytes_f64 / kb, prefixes[0])
+    } else if bytes_f64 < gb {
+        format!("{:.2} {}", bytes_f64 / mb, prefixes[1])
     } else {
-        format!("{:.2} GB", bytes_f64 / GB)
+        format!("{:.2} {}", bytes_f64 / gb, prefixes[2])
+    }
+}
+
+fn format_rate(bytes_per_sec: f64, units: Units) -> String {
+    let (scale, unit) = rate_unit(bytes_per_sec, units);
+    format!("{:.2} {}", bytes_per_sec / scale, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::scripted::ScriptedCollector;
+    use crate::network::{get_network_info, AddressScope, Interface, InterfaceAddress, IpFamily, Rule};
+    use crossterm::event::KeyCode;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    async fn scripted_info() -> NetworkInfo {
+        let mut eth0 = Interface::new("eth0", 2);
+        eth0.status = true;
+        let address = "192.0.2.10".parse().unwrap();
+        eth0.ipv4_addresses.push(InterfaceAddress {
+            address,
+            prefix_len: 24,
+            scope: AddressScope::of(&address),
+            flags: Default::default(),
+        });
+        let collector = ScriptedCollector::new(vec![eth0, Interface::new("wlan0", 3)])
+            .with_counters("eth0", [(1000, 2000)])
+            .with_public_ip("203.0.113.7".parse().unwrap());
+        let mut info = get_network_info(&collector, &Config::default()).await.unwrap();
+        info.hostname = "testhost".to_string();
+        info
+    }
+
+    /// Draws one frame and returns the screen as lines of text.
+    fn draw(info: &NetworkInfo, config: &Config, app: &mut App, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| render_ui(f, info, config, app)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| (0..width).map(|x| buffer.get(x, y).symbol.as_str()).collect::<String>())
+            .collect()
+    }
+
+    fn shows(screen: &[String], text: &str) -> bool {
+        screen.iter().any(|line| line.contains(text))
+    }
+
+    #[tokio::test]
+    async fn interfaces_view() {
+        let info = scripted_info().await;
+        let config = Config::default();
+        let mut app = App::new(&config);
+
+        let screen = draw(&info, &config, &mut app, 120, 40);
+        assert!(shows(&screen, "Network Information for testhost"), "{:#?}", screen);
+        assert!(shows(&screen, "1 Interfaces"));
+        assert!(shows(&screen, "eth0"));
+        assert!(shows(&screen, "wlan0"));
+        assert!(shows(&screen, "192.0.2.10/24"));
+        assert!(shows(&screen, "203.0.113.7"));
+
+        app.handle_key(KeyCode::Enter, &info);
+        let screen = draw(&info, &config, &mut app, 120, 40);
+        assert!(shows(&screen, "┌ eth0 ─"), "{:#?}", screen);
+        assert!(shows(&screen, "Addresses (1)"));
+        assert!(shows(&screen, "192.0.2.10/24 (global)"));
+        assert!(shows(&screen, "ESC/Enter: back to list"));
+    }
+
+    #[tokio::test]
+    async fn rules_scroll_in_their_own_pane() {
+        let mut info = scripted_info().await;
+        info.rules = (0..12)
+            .map(|priority| Rule {
+                family: IpFamily::V4,
+                priority: priority * 100,
+                selector: "from all".to_string(),
+                action: format!("lookup t{}", priority),
+            })
+            .collect();
+        let config = Config::default();
+        let mut app = App::new(&config);
+        app.handle_key(KeyCode::Char('2'), &info);
+
+        let screen = draw(&info, &config, &mut app, 120, 40);
+        assert!(shows(&screen, "Policy Rules (1-8 of 12)"), "{:#?}", screen);
+        assert!(shows(&screen, "lookup t7"));
+        assert!(!shows(&screen, "lookup t8"));
+
+        app.handle_key(KeyCode::Tab, &info);
+        app.handle_key(KeyCode::End, &info);
+        let screen = draw(&info, &config, &mut app, 120, 40);
+        assert!(shows(&screen, "Policy Rules (5-12 of 12)"), "{:#?}", screen);
+        assert!(shows(&screen, "lookup t11"));
+        assert!(!shows(&screen, "lookup t3 "));
+    }
+
+    #[tokio::test]
+    async fn tiny_terminals_do_not_panic() {
+        let info = scripted_info().await;
+        let config = Config::default();
+        for view in ['1', '2', '3', '4', '5'] {
+            let mut app = App::new(&config);
+            app.handle_key(KeyCode::Char(view), &info);
+            draw(&info, &config, &mut app, 20, 8);
+            draw(&info, &config, &mut app, 1, 1);
+        }
     }
 }
@@ -0,0 +1,55 @@
+use super::{render_table, ViewTable};
+use crate::app::App;
+use crate::network::NetworkInfo;
+use ratatui::{
+    backend::Backend,
+    layout::{Constraint, Rect},
+    style::{Color, Style},
+    widgets::{Cell, Row},
+    Frame,
+};
+
+/// Open TCP and UDP sockets, listening ones first, then by local port.
+pub fn render_connections<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &NetworkInfo, app: &mut App) {
+    let mut connections: Vec<_> = network_info.connections.iter().collect();
+    connections.sort_by_key(|connection| {
+        (!connection.is_listening(), connection.protocol, connection.local.port(), connection.remote)
+    });
+    let listening = connections.iter().filter(|connection| connection.is_listening()).count();
+
+    let rows = connections
+        .iter()
+        .map(|connection| {
+            let state_color = match connection.state {
+                "ESTAB" => Color::Green,
+                "LISTEN" | "UNCONN" => Color::Cyan,
+                _ => Color::Yellow,
+            };
+            let remote = connection.remote.map_or_else(|| "*".to_string(), |remote| remote.to_string());
+            Row::new(vec![
+                Cell::from(connection.protocol.label()),
+                Cell::from(connection.local.to_string()),
+                Cell::from(remote),
+                Cell::from(connection.state).style(Style::default().fg(state_color)),
+                Cell::from(connection.uid.to_string()).style(Style::default().fg(Color::DarkGray)),
+            ])
+        })
+        .collect();
+
+    let header = Row::new(vec!["Proto", "Local", "Remote", "State", "UID"]);
+    let widths = [
+        Constraint::Length(5),
+        Constraint::Percentage(35),
+        Constraint::Percentage(35),
+        Constraint::Length(11),
+        Constraint::Length(6),
+    ];
+    let table = ViewTable {
+        title: format!(" Connections ({}, {} listening) ", connections.len(), listening),
+        header,
+        rows,
+        widths: &widths,
+        empty: "No open sockets",
+    };
+    render_table(f, area, app, table);
+}
@@ -0,0 +1,108 @@
+use super::{render_table, ViewTable};
+use crate::app::App;
+use crate::config::Config;
+use crate::network::NetworkInfo;
+use ratatui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Cell, Paragraph, Row},
+    Frame,
+};
+
+/// Nameservers, search domains and options of the stub resolver, what
+/// systemd-resolved forwards to, per-link servers and `/etc/hosts` entries.
+pub fn render_dns<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &NetworkInfo, config: &Config, app: &mut App) {
+    let resolver = &network_info.resolver;
+    let setting = |source: String, name: String, value: String, color: Color| {
+        Row::new(vec![
+            Cell::from(source).style(Style::default().fg(Color::Magenta)),
+            Cell::from(name).style(Style::default().fg(Color::Blue)),
+            Cell::from(value).style(Style::default().fg(color)),
+        ])
+    };
+    let resolv_conf = || "resolv.conf".to_string();
+
+    let mut rows: Vec<Row> = resolver
+        .nameservers
+        .iter()
+        .map(|nameserver| setting(resolv_conf(), "Nameserver".to_string(), nameserver.clone(), Color::Green))
+        .collect();
+    if !resolver.search.is_empty() {
+        rows.push(setting(resolv_conf(), "Search".to_string(), resolver.search.join(" "), Color::Cyan));
+    }
+    if !resolver.options.is_empty() {
+        rows.push(setting(resolv_conf(), "Options".to_string(), resolver.options.join(" "), Color::White));
+    }
+    for upstream in &resolver.upstream {
+        rows.push(setting("resolved".to_string(), "Upstream".to_string(), upstream.clone(), Color::Green));
+    }
+    for link in &resolver.links {
+        let interface = network_info
+            .interfaces
+            .iter()
+            .find(|interface| interface.index == link.index)
+            .map_or_else(|| format!("link {}", link.index), |interface| config.display_name(&interface.name));
+        if !link.servers.is_empty() {
+            rows.push(setting(interface.clone(), "Servers".to_string(), link.servers.join(" "), Color::Green));
+        }
+        if !link.domains.is_empty() {
+            rows.push(setting(interface, "Domains".to_string(), link.domains.join(" "), Color::Cyan));
+        }
+    }
+    for entry in &resolver.hosts {
+        // The stock localhost and multicast lines are there on every host;
+        // dim them so real overrides stand out.
+        let color = match entry.address.is_loopback() || entry.address.is_multicast() {
+            true => Color::DarkGray,
+            false => Color::Yellow,
+        };
+        rows.push(setting("hosts".to_string(), entry.address.to_string(), entry.names.join(" "), color));
+    }
+
+    let header = Row::new(vec!["Source", "Setting", "Value"]);
+    let widths = [Constraint::Length(12), Constraint::Length(26), Constraint::Percentage(100)];
+    let table = ViewTable {
+        title: match resolver.uses_resolved_stub() {
+            true => " Resolver (/etc/resolv.conf via systemd-resolved) ".to_string(),
+            false => " Resolver (/etc/resolv.conf) ".to_string(),
+        },
+        header,
+        rows,
+        widths: &widths,
+        empty: "No resolver configuration",
+    };
+    render_table(f, area, app, table);
+}
+
+/// One line next to the hostname: the servers queries end up at and the
+/// search domains.
+pub fn render_dns_summary<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &NetworkInfo) {
+    let resolver = &network_info.resolver;
+    let servers = match resolver.effective_servers() {
+        [] => "none".to_string(),
+        servers => servers.join(", "),
+    };
+
+    let mut spans = vec![
+        Span::styled("DNS ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+        Span::styled(servers, Style::default().fg(Color::Green)),
+    ];
+    if !resolver.upstream.is_empty() {
+        spans.push(Span::styled(" via resolved", Style::default().fg(Color::DarkGray)));
+    }
+    if !resolver.search.is_empty() {
+        spans.push(Span::styled("  search ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)));
+        spans.push(Span::styled(resolver.search.join(" "), Style::default().fg(Color::White)));
+    }
+
+    let summary = Paragraph::new(Spans::from(spans))
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title("Resolver")
+            .border_style(Style::default().fg(Color::Blue)))
+        .alignment(Alignment::Center);
+
+    f.render_widget(summary, area);
+}
@@ -0,0 +1,746 @@
+use super::{format_age, format_bytes, format_rate, geo_summary, pane_style, rate_unit};
+use crate::app::{App, Pane};
+use crate::config::{Config, Units};
+use crate::network::{default_routes, Interface, InterfaceAddress, IpFamily, NatMapping, NetworkInfo, Route, StunReport};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::SystemTime;
+use ratatui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+    Frame,
+};
+
+pub fn render_interfaces<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &NetworkInfo, config: &Config, app: &mut App) {
+    let stun = network_info.stun.as_ref().filter(|_| config.panels.stun);
+    let public_ip = &network_info.public_ip;
+    let public_ip_lines = match config.panels.public_ip {
+        true => {
+            let geo_lines = public_ip.v4.iter().chain(&public_ip.v6).filter(|public| public.geo.is_some()).count();
+            1 + geo_lines as u16 + u16::from(!public_ip.disagreements.is_empty())
+        }
+        false => 0,
+    };
+    let stun_lines = match stun {
+        Some(stun) if stun.mapping == NatMapping::AddressPortDependent => 3,
+        Some(_) => 2,
+        None => 0,
+    };
+    let public_ip_height = match public_ip_lines.max(stun_lines) {
+        0 => 0,
+        lines => lines + 2,
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(public_ip_height),
+            Constraint::Min(0),
+        ])
+        .split(area);
+    
+    match (config.panels.public_ip, stun) {
+        (true, Some(stun)) => {
+            let row = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .split(chunks[0]);
+            render_public_ip(f, row[0], network_info);
+            render_stun(f, row[1], stun);
+        }
+        (true, None) => render_public_ip(f, chunks[0], network_info),
+        (false, Some(stun)) => render_stun(f, chunks[0], stun),
+        (false, None) => {}
+    }
+    
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(chunks[1]);
+
+    render_interface_list(f, panes[0], network_info, config, app);
+
+    if let Some(interface) = app.selected_interface(network_info) {
+        render_interface(f, panes[1], interface, network_info, config, app);
+    }
+}
+
+/// One line per interface with its state, first address and current rates,
+/// scrolled so the selected interface stays visible.
+fn render_interface_list<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    network_info: &NetworkInfo,
+    config: &Config,
+    app: &mut App,
+) {
+    let rows: Vec<Row> = network_info
+        .interfaces
+        .iter()
+        .map(|interface| {
+            let (status_text, status_color) = match interface.status {
+                true => ("up", Color::Green),
+                false => ("down", Color::Red),
+            };
+            let address = interface
+                .ipv4_addresses
+                .first()
+                .or(interface.ipv6_addresses.first())
+                .map_or_else(|| "-".to_string(), |address| address.to_string());
+            Row::new(vec![
+                Cell::from(config.display_name(&interface.name)),
+                Cell::from(status_text).style(Style::default().fg(status_color)),
+                Cell::from(address).style(Style::default().fg(Color::Green)),
+                Cell::from(format_rate(interface.usage.rx_rate(), config.units)).style(Style::default().fg(Color::Green)),
+                Cell::from(format_rate(interface.usage.tx_rate(), config.units)).style(Style::default().fg(Color::Red)),
+            ])
+        })
+        .collect();
+
+    // Borders and the header row.
+    app.set_page_size(Pane::Interfaces, area.height.saturating_sub(3) as usize);
+    // Whatever the borders, highlight symbol, column gaps and fixed-width
+    // columns leave is shared between names and addresses.
+    let flexible = area.width.saturating_sub(2 + 2 + 4 + 5 + 11 + 11);
+    let name_width = flexible * 2 / 5;
+    let widths = [
+        Constraint::Length(name_width),
+        Constraint::Length(5),
+        Constraint::Length(flexible - name_width),
+        Constraint::Length(11),
+        Constraint::Length(11),
+    ];
+
+    let table = Table::new(rows)
+        .header(Row::new(vec![
+            Cell::from("Interface"),
+            Cell::from("State"),
+            Cell::from("Address"),
+            Cell::from("RX"),
+            Cell::from("TX"),
+        ]).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Interfaces ({}) ", network_info.interfaces.len()))
+            .border_style(pane_style(app, Pane::Interfaces, Color::Cyan)))
+        .widths(&widths)
+        .column_spacing(1)
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ")
+        .style(Style::default().fg(Color::White));
+
+    f.render_stateful_widget(table, area, &mut app.interface_table);
+}
+
+fn render_public_ip<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &NetworkInfo) {
+    let mut spans = Vec::new();
+    let families = [
+        ("IPv4: ", IpFamily::V4, &network_info.public_ip.v4),
+        ("IPv6: ", IpFamily::V6, &network_info.public_ip.v6),
+    ];
+    for (label, family, public) in families {
+        if !spans.is_empty() {
+            spans.push(Span::raw("    "));
+        }
+        spans.push(Span::styled(
+            label,
+            Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+        ));
+        match public {
+            Some(public) => {
+                spans.push(Span::styled(
+                    public.address.to_string(),
+                    Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+                ));
+                if let Some(hostname) = network_info.hostnames.get(&public.address) {
+                    spans.push(Span::styled(format!(" {}", hostname), Style::default().fg(Color::Gray)));
+                }
+                spans.push(Span::styled(
+                    format!(" ({})", public.source()),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            None if network_info.public_ip_pending => spans.push(Span::styled(
+                "Looking up...",
+                Style::default().fg(Color::DarkGray),
+            )),
+            None if network_info.public_ip.no_majority(family) => spans.push(Span::styled(
+                "No majority",
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            )),
+            None => spans.push(Span::styled(
+                "Unknown",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )),
+        }
+    }
+
+    let mut text = vec![Spans::from(spans)];
+
+    for (label, public) in [("IPv4: ", &network_info.public_ip.v4), ("IPv6: ", &network_info.public_ip.v6)] {
+        if let Some(geo) = public.as_ref().and_then(|public| public.geo.as_ref()) {
+            text.push(Spans::from(vec![
+                Span::styled(label, Style::default().fg(Color::DarkGray)),
+                Span::styled(geo_summary(geo), Style::default().fg(Color::Cyan)),
+            ]));
+        }
+    }
+
+    let disagreements = &network_info.public_ip.disagreements;
+    if !disagreements.is_empty() {
+        let seen = disagreements
+            .iter()
+            .map(|answer| match &answer.geo {
+                Some(geo) => format!("{} ({}, {})", answer.address, answer.provider_host(), geo_summary(geo)),
+                None => format!("{} ({})", answer.address, answer.provider_host()),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        text.push(Spans::from(vec![
+            Span::styled("Providers disagree: ", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(seen, Style::default().fg(Color::Yellow)),
+        ]));
+    }
+    
+    let mut title = vec![Span::raw(" External IP ")];
+    if let Some(changed_at) = network_info.public_ip_history.changed_at() {
+        let age = SystemTime::now().duration_since(changed_at).unwrap_or_default();
+        title.push(Span::styled(
+            format!("· changed {} ", format_age(age)),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    
+    let paragraph = Paragraph::new(text)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(Spans::from(title))
+            .border_style(Style::default().fg(Color::Magenta)))
+        .style(Style::default().fg(Color::White))
+        .alignment(ratatui::layout::Alignment::Center);
+    
+    f.render_widget(paragraph, area);
+}
+
+fn render_stun<B: Backend>(f: &mut Frame<B>, area: Rect, stun: &StunReport) {
+    let label = Style::default().fg(Color::White).add_modifier(Modifier::BOLD);
+    let mut spans = vec![Span::styled("Mapped: ", label)];
+    match stun.mapped_address() {
+        Some(address) => spans.push(Span::styled(
+            address.to_string(),
+            Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+        )),
+        None => spans.push(Span::styled(
+            "Unknown",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )),
+    }
+    let mut text = vec![Spans::from(spans)];
+
+    let mapping_color = match stun.mapping {
+        NatMapping::NoNat | NatMapping::EndpointIndependent => Color::Green,
+        NatMapping::AddressPortDependent => Color::Yellow,
+        NatMapping::Unknown => Color::DarkGray,
+    };
+    text.push(Spans::from(vec![
+        Span::styled("NAT Mapping: ", label),
+        Span::styled(stun.mapping.label(), Style::default().fg(mapping_color)),
+    ]));
+    if let Some(second) = stun.mappings.get(1).filter(|_| stun.mapping == NatMapping::AddressPortDependent) {
+        text.push(Spans::from(vec![Span::styled(
+            format!("{} saw {}", second.server, second.mapped_address),
+            Style::default().fg(Color::Yellow),
+        )]));
+    }
+
+    let paragraph = Paragraph::new(text)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(" STUN ")
+            .border_style(Style::default().fg(Color::Magenta)))
+        .style(Style::default().fg(Color::White))
+        .alignment(ratatui::layout::Alignment::Center);
+
+    f.render_widget(paragraph, area);
+}
+
+fn render_interface<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    interface: &Interface,
+    network_info: &NetworkInfo,
+    config: &Config,
+    app: &mut App,
+) {
+    if !config.panels.graphs {
+        render_interface_info(f, area, interface, network_info, config, app);
+        return;
+    }
+    
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(8),
+            Constraint::Length(7),
+        ])
+        .split(area);
+    
+    render_interface_info(f, chunks[0], interface, network_info, config, app);
+    
+    render_network_graph(f, chunks[1], interface, config.units, false);
+}
+
+fn render_interface_info<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    interface: &Interface,
+    network_info: &NetworkInfo,
+    config: &Config,
+    app: &mut App,
+) {
+    let hostnames = &network_info.hostnames;
+    let mut rows = Vec::new();
+    
+    let status_color = if interface.status { Color::Green } else { Color::Red };
+    let status_text = if interface.status { "up" } else { "down" };
+    rows.push(Row::new(vec![
+        Cell::from("Status").style(Style::default().fg(Color::Cyan)),
+        Cell::from(status_text).style(Style::default().fg(status_color).add_modifier(Modifier::BOLD)),
+    ]));
+    
+    if let Some(gateways) = default_gateways(&network_info.routes, &interface.name) {
+        rows.push(Row::new(vec![
+            Cell::from("Default Gateway").style(Style::default().fg(Color::Cyan)),
+            Cell::from(gateways).style(Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+        ]));
+    }
+    
+    if let Some(mac) = &interface.mac_address {
+        rows.push(Row::new(vec![
+            Cell::from("MAC Address").style(Style::default().fg(Color::Cyan)),
+            Cell::from(mac.as_str()).style(Style::default().fg(Color::Yellow)),
+        ]));
+    }
+    
+    if let Some(mtu) = &interface.mtu {
+        rows.push(Row::new(vec![
+            Cell::from("MTU").style(Style::default().fg(Color::Cyan)),
+            Cell::from(mtu.to_string()).style(Style::default().fg(Color::White)),
+        ]));
+    }
+    
+    if let Some(speed) = &interface.speed {
+        rows.push(Row::new(vec![
+            Cell::from("Speed").style(Style::default().fg(Color::Cyan)),
+            Cell::from(format!("{} Mbps", speed)).style(Style::default().fg(Color::White)),
+        ]));
+    }
+    
+    if interface.received_bytes > 0 {
+        rows.push(Row::new(vec![
+            Cell::from("RX Bytes").style(Style::default().fg(Color::Cyan)),
+            Cell::from(format_bytes(interface.received_bytes, config.units)).style(Style::default().fg(Color::Magenta)),
+        ]));
+    }
+    
+    if interface.transmitted_bytes > 0 {
+        rows.push(Row::new(vec![
+            Cell::from("TX Bytes").style(Style::default().fg(Color::Cyan)),
+            Cell::from(format_bytes(interface.transmitted_bytes, config.units)).style(Style::default().fg(Color::Magenta)),
+        ]));
+    }
+    
+    if !interface.usage.history.is_empty() {
+        rows.push(Row::new(vec![
+            Cell::from("RX Rate").style(Style::default().fg(Color::Cyan)),
+            Cell::from(format_rate(interface.usage.rx_rate(), config.units)).style(Style::default().fg(Color::Green)),
+        ]));
+        rows.push(Row::new(vec![
+            Cell::from("TX Rate").style(Style::default().fg(Color::Cyan)),
+            Cell::from(format_rate(interface.usage.tx_rate(), config.units)).style(Style::default().fg(Color::Red)),
+        ]));
+    }
+    
+    for (i, addr) in interface.ipv4_addresses.iter().enumerate() {
+        let prefix = if i == 0 { "IPv4 Address" } else { "" };
+        rows.push(Row::new(vec![
+            Cell::from(prefix).style(Style::default().fg(Color::Cyan)),
+            Cell::from(address_spans(addr, hostnames, Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))),
+        ]));
+    }
+    
+    for (i, addr) in interface.ipv6_addresses.iter().enumerate() {
+        let prefix = if i == 0 { "IPv6 Address" } else { "" };
+        rows.push(Row::new(vec![
+            Cell::from(prefix).style(Style::default().fg(Color::Cyan)),
+            Cell::from(address_spans(addr, hostnames, Style::default().fg(Color::Blue))),
+        ]));
+    }
+    
+    if let Some(public_ip) = &interface.public_ip {
+        let answers = [("Public IPv4", &public_ip.v4), ("Public IPv6", &public_ip.v6)];
+        if answers.iter().all(|(_, public)| public.is_none()) {
+            rows.push(Row::new(vec![
+                Cell::from("Public IP").style(Style::default().fg(Color::Cyan)),
+                Cell::from("unknown").style(Style::default().fg(Color::Red)),
+            ]));
+        }
+        for (label, public) in answers {
+            let Some(public) = public else {
+                continue;
+            };
+            let mut spans = vec![Span::styled(
+                public.address.to_string(),
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            )];
+            if let Some(hostname) = hostnames.get(&public.address) {
+                spans.push(Span::styled(format!(" {}", hostname), Style::default().fg(Color::Gray)));
+            }
+            spans.push(Span::styled(format!(" ({})", public.source()), Style::default().fg(Color::DarkGray)));
+            rows.push(Row::new(vec![
+                Cell::from(label).style(Style::default().fg(Color::Cyan)),
+                Cell::from(Spans::from(spans)),
+            ]));
+        }
+    }
+    
+    // Borders and the header row.
+    let visible = area.height.saturating_sub(3) as usize;
+    app.set_page_size(Pane::Details, visible);
+    app.details_scroll = app.details_scroll.min(rows.len().saturating_sub(visible));
+    let rows: Vec<Row> = rows.into_iter().skip(app.details_scroll).collect();
+    
+    let table = Table::new(rows)
+        .header(Row::new(vec![
+            Cell::from("Property").style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Cell::from("Value").style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        ]))
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} ", config.display_name(&interface.name)))
+            .border_style(pane_style(app, Pane::Details, Color::Cyan)))
+        .widths(&[
+            Constraint::Percentage(30),
+            Constraint::Percentage(70),
+        ])
+        .column_spacing(1)
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .style(Style::default().fg(Color::White));
+    
+    f.render_widget(table, area);
+}
+
+/// Everything known about the selected interface, filling the area the list
+/// and panels normally take.
+pub fn render_interface_detail<B: Backend>(
+    f: &mut Frame<B>,
+    area: Rect,
+    network_info: &NetworkInfo,
+    config: &Config,
+    app: &mut App,
+) {
+    let Some(interface) = app.selected_interface(network_info) else {
+        return;
+    };
+    let label = |text: &'static str| Cell::from(text).style(Style::default().fg(Color::Cyan));
+    let none = || Cell::from("-").style(Style::default().fg(Color::DarkGray));
+
+    let mut link_rows = vec![
+        Row::new(vec![label("Index"), Cell::from(interface.index.to_string())]),
+        Row::new(vec![
+            label("State"),
+            match interface.status {
+                true => Cell::from("up").style(Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+                false => Cell::from("down").style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            },
+        ]),
+        Row::new(vec![
+            label("Flags"),
+            match interface.link_flags.is_empty() {
+                true => none(),
+                false => Cell::from(interface.link_flags.join(",")),
+            },
+        ]),
+        Row::new(vec![
+            label("Default Gateway"),
+            default_gateways(&network_info.routes, &interface.name)
+                .map_or_else(none, |gateways| Cell::from(gateways).style(Style::default().fg(Color::Green))),
+        ]),
+        Row::new(vec![label("Driver"), interface.driver.as_deref().map_or_else(none, Cell::from)]),
+        Row::new(vec![
+            label("MAC Address"),
+            interface.mac_address.as_deref().map_or_else(none, |mac| Cell::from(mac).style(Style::default().fg(Color::Yellow))),
+        ]),
+        Row::new(vec![label("MTU"), interface.mtu.map_or_else(none, |mtu| Cell::from(mtu.to_string()))]),
+        Row::new(vec![label("Speed"), interface.speed.map_or_else(none, |speed| Cell::from(format!("{} Mbps", speed)))]),
+    ];
+    if let Some(public_ip) = &interface.public_ip {
+        for (name, public) in [("Public IPv4", &public_ip.v4), ("Public IPv6", &public_ip.v6)] {
+            if let Some(public) = public {
+                link_rows.push(Row::new(vec![
+                    label(name),
+                    Cell::from(format!("{} ({})", public.address, public.source())).style(Style::default().fg(Color::Yellow)),
+                ]));
+            }
+        }
+    }
+
+    let count = |value: u64| Cell::from(value.to_string());
+    let counter_rows = match &interface.packets {
+        Some(packets) => vec![
+            Row::new(vec![
+                label("Bytes"),
+                Cell::from(format_bytes(interface.received_bytes, config.units)),
+                Cell::from(format_bytes(interface.transmitted_bytes, config.units)),
+            ]),
+            Row::new(vec![label("Packets"), count(packets.rx_packets), count(packets.tx_packets)]),
+            Row::new(vec![label("Errors"), count(packets.rx_errors), count(packets.tx_errors)]),
+            Row::new(vec![label("Dropped"), count(packets.rx_dropped), count(packets.tx_dropped)]),
+        ],
+        None => vec![Row::new(vec![
+            label("Bytes"),
+            Cell::from(format_bytes(interface.received_bytes, config.units)),
+            Cell::from(format_bytes(interface.transmitted_bytes, config.units)),
+        ])],
+    };
+
+    let addresses: Vec<&InterfaceAddress> = interface.ipv4_addresses.iter().chain(&interface.ipv6_addresses).collect();
+    let address_lines = addresses.len().clamp(1, 8) as u16;
+    let top_height = link_rows.len().max(counter_rows.len() + 1) as u16 + 2;
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(top_height),
+            Constraint::Length(address_lines + 2),
+            Constraint::Length(5),
+            Constraint::Min(8),
+        ])
+        .split(area);
+    let top = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .split(chunks[0]);
+
+    let link = Table::new(link_rows)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} ", config.display_name(&interface.name)))
+            .border_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
+        .widths(&[Constraint::Length(15), Constraint::Percentage(100)])
+        .column_spacing(1)
+        .style(Style::default().fg(Color::White));
+    f.render_widget(link, top[0]);
+
+    let counters = Table::new(counter_rows)
+        .header(Row::new(vec!["", "RX", "TX"]).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(" Counters ")
+            .border_style(Style::default().fg(Color::Cyan)))
+        .widths(&[Constraint::Length(8), Constraint::Percentage(50), Constraint::Percentage(50)])
+        .column_spacing(1)
+        .style(Style::default().fg(Color::White));
+    f.render_widget(counters, top[1]);
+
+    let visible = address_lines as usize;
+    app.set_page_size(Pane::Details, visible);
+    app.details_scroll = app.details_scroll.min(addresses.len().saturating_sub(visible));
+    let address_text: Vec<Spans> = match addresses.is_empty() {
+        true => vec![Spans::from(Span::styled("No addresses", Style::default().fg(Color::DarkGray)))],
+        false => addresses
+            .iter()
+            .skip(app.details_scroll)
+            .map(|addr| {
+                let color = match addr.address {
+                    IpAddr::V4(_) => Color::Green,
+                    IpAddr::V6(_) => Color::Blue,
+                };
+                address_spans(addr, &network_info.hostnames, Style::default().fg(color))
+            })
+            .collect(),
+    };
+    let address_list = Paragraph::new(address_text)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Addresses ({}) ", addresses.len()))
+            .border_style(Style::default().fg(Color::Cyan)));
+    f.render_widget(address_list, chunks[1]);
+
+    render_rate_summary(f, chunks[2], interface, config.units);
+
+    render_network_graph(f, chunks[3], interface, config.units, true);
+}
+
+/// Current, minimum, average, maximum and percentile rates over the graph's
+/// history window.
+fn render_rate_summary<B: Backend>(f: &mut Frame<B>, area: Rect, interface: &Interface, units: Units) {
+    let usage = &interface.usage;
+    let rows = [
+        ("RX", usage.rx_rate(), usage.rx_summary(), Color::Green),
+        ("TX", usage.tx_rate(), usage.tx_summary(), Color::Red),
+    ]
+    .into_iter()
+    .map(|(direction, current, summary, color)| {
+        let mut cells = vec![
+            Cell::from(direction).style(Style::default().fg(color).add_modifier(Modifier::BOLD)),
+            Cell::from(format_rate(current, units)),
+        ];
+        match summary {
+            Some(summary) => cells.extend(
+                [summary.min, summary.avg, summary.max, summary.p50, summary.p95]
+                    .map(|rate| Cell::from(format_rate(rate, units))),
+            ),
+            None => cells.extend(std::iter::repeat(Cell::from("-")).take(5)),
+        }
+        Row::new(cells)
+    });
+
+    let table = Table::new(rows)
+        .header(
+            Row::new(vec!["", "Current", "Min", "Avg", "Max", "Median", "95th %"])
+                .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Rates over the last {} ", format_window(usage.window_secs())))
+            .border_style(Style::default().fg(Color::Cyan)))
+        .widths(&[
+            Constraint::Length(3),
+            Constraint::Ratio(1, 6),
+            Constraint::Ratio(1, 6),
+            Constraint::Ratio(1, 6),
+            Constraint::Ratio(1, 6),
+            Constraint::Ratio(1, 6),
+            Constraint::Ratio(1, 6),
+        ])
+        .column_spacing(1)
+        .style(Style::default().fg(Color::White));
+    f.render_widget(table, area);
+}
+
+/// A span of seconds in the largest unit that keeps it readable, e.g. `45s`
+/// or `5m`.
+fn format_window(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    match secs {
+        0..=119 => format!("{}s", secs),
+        120..=7199 => format!("{}m", secs / 60),
+        _ => format!("{}h", secs / 3600),
+    }
+}
+
+/// Draws the RX and TX history. With `time_labels` the time axis is labelled
+/// from the start of the window to now, which only the full-screen view has
+/// room for.
+fn render_network_graph<B: Backend>(f: &mut Frame<B>, area: Rect, interface: &Interface, units: Units, time_labels: bool) {
+    use ratatui::widgets::{Dataset, Chart, Axis};
+    use ratatui::symbols;
+    
+    let usage = &interface.usage;
+    let max_y = f64::max(usage.max_rx, usage.max_tx) * 1.2;
+    
+    let (scale, y_label) = rate_unit(max_y, units);
+    let y_max = max_y / scale;
+    
+    let newest = usage.history.back().map(|sample| sample.at);
+    let seconds_ago = |at: std::time::Instant| {
+        newest.map_or(0.0, |newest| -newest.duration_since(at).as_secs_f64())
+    };
+    
+    let rx_data: Vec<(f64, f64)> = usage.history
+        .iter()
+        .map(|sample| (seconds_ago(sample.at), sample.rx / scale))
+        .collect();
+    
+    let tx_data: Vec<(f64, f64)> = usage.history
+        .iter()
+        .map(|sample| (seconds_ago(sample.at), sample.tx / scale))
+        .collect();
+    
+    let datasets = vec![
+        Dataset::default()
+            .name("RX")
+            .marker(symbols::Marker::Braille)
+            .style(Style::default().fg(Color::Green))
+            .data(&rx_data),
+        Dataset::default()
+            .name("TX")
+            .marker(symbols::Marker::Braille)
+            .style(Style::default().fg(Color::Red))
+            .data(&tx_data),
+    ];
+    
+    let chart = Chart::new(datasets)
+        .block(Block::default()
+            .title(format!(" Network Traffic ({}) ", y_label))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)))
+        .x_axis(Axis::default()
+            .title("Time")
+            .style(Style::default().fg(Color::Gray))
+            .bounds([-usage.window_secs(), 0.0])
+            .labels(match time_labels {
+                true => {
+                    let window = usage.window_secs();
+                    vec![
+                        format!("-{}", format_window(window)).into(),
+                        format!("-{}", format_window(window / 2.0)).into(),
+                        "now".into(),
+                    ]
+                }
+                false => vec![],
+            }))
+        .y_axis(Axis::default()
+            .title("Rate")
+            .style(Style::default().fg(Color::Gray))
+            .bounds([0.0, y_max])
+            .labels(vec![
+                "0".into(),
+                format!("{:.1}", y_max / 4.0).into(),
+                format!("{:.1}", y_max / 2.0).into(),
+                format!("{:.1}", y_max * 3.0 / 4.0).into(),
+                format!("{:.1}", y_max).into(),
+            ]));
+    
+    f.render_widget(chart, area);
+}
+
+/// Next hops of the default routes leaving through `interface`, main table
+/// first, e.g. `192.0.2.1, fe80::1 (table vpn)`, or `None` if it carries no
+/// default route. A default route without a gateway, as on point-to-point
+/// tunnels, is `direct`.
+fn default_gateways(routes: &[Route], interface: &str) -> Option<String> {
+    let mut routes: Vec<&Route> = default_routes(routes, interface)
+        .filter(|route| route.kind == "unicast")
+        .collect();
+    routes.sort_by_key(|route| !route.in_main_table());
+    let gateways: Vec<String> = routes
+        .iter()
+        .map(|route| {
+            let gateway = route.gateway.map_or_else(|| "direct".to_string(), |gateway| gateway.to_string());
+            match route.in_main_table() {
+                true => gateway,
+                false => format!("{} (table {})", gateway, route.table),
+            }
+        })
+        .collect();
+    (!gateways.is_empty()).then(|| gateways.join(", "))
+}
+
+/// An address followed by its PTR name, if one has been resolved.
+fn address_spans<'a>(addr: &InterfaceAddress, hostnames: &'a HashMap<IpAddr, String>, style: Style) -> Spans<'a> {
+    let mut spans = vec![Span::styled(format_address(addr), style)];
+    if let Some(hostname) = hostnames.get(&addr.address) {
+        spans.push(Span::styled(format!("  {}", hostname), Style::default().fg(Color::Gray)));
+    }
+    Spans::from(spans)
+}
+
+fn format_address(addr: &InterfaceAddress) -> String {
+    let mut labels = vec![addr.scope.label()];
+    labels.extend(addr.flags.labels());
+    format!("{} ({})", addr, labels.join(", "))
+}
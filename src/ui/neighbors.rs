@@ -0,0 +1,53 @@
+use super::{render_table, ViewTable};
+use crate::app::App;
+use crate::config::Config;
+use crate::network::NetworkInfo;
+use ratatui::{
+    backend::Backend,
+    layout::{Constraint, Rect},
+    style::{Color, Style},
+    widgets::{Cell, Row},
+    Frame,
+};
+
+/// The ARP and NDP neighbor caches, by interface and then address.
+pub fn render_neighbors<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &NetworkInfo, config: &Config, app: &mut App) {
+    let mut neighbors: Vec<_> = network_info.neighbors.iter().collect();
+    neighbors.sort_by(|a, b| (&a.interface, a.address).cmp(&(&b.interface, b.address)));
+
+    let rows = neighbors
+        .iter()
+        .map(|neighbor| {
+            let state_color = match neighbor.state {
+                "REACHABLE" | "PERMANENT" => Color::Green,
+                "INCOMPLETE" | "FAILED" => Color::Red,
+                _ => Color::Yellow,
+            };
+            Row::new(vec![
+                Cell::from(neighbor.address.to_string()),
+                Cell::from(neighbor.mac_address.clone().unwrap_or_else(|| "-".to_string()))
+                    .style(Style::default().fg(Color::Cyan)),
+                Cell::from(config.display_name(&neighbor.interface)),
+                Cell::from(neighbor.state).style(Style::default().fg(state_color)),
+                Cell::from(if neighbor.router { "router" } else { "" }).style(Style::default().fg(Color::DarkGray)),
+            ])
+        })
+        .collect();
+
+    let header = Row::new(vec!["Address", "MAC Address", "Interface", "State", ""]);
+    let widths = [
+        Constraint::Percentage(35),
+        Constraint::Length(17),
+        Constraint::Percentage(20),
+        Constraint::Length(10),
+        Constraint::Length(6),
+    ];
+    let table = ViewTable {
+        title: format!(" Neighbors ({}) ", neighbors.len()),
+        header,
+        rows,
+        widths: &widths,
+        empty: "No neighbor entries",
+    };
+    render_table(f, area, app, table);
+}
@@ -0,0 +1,134 @@
+use super::{pane_style, render_table, ViewTable};
+use crate::app::{App, Pane};
+use crate::config::Config;
+use crate::network::{NetworkInfo, Route};
+use ratatui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, Row, Table},
+    Frame,
+};
+
+/// Most rule rows shown at once above the routes; longer rule lists scroll
+/// in their own pane.
+const MAX_RULE_ROWS: usize = 8;
+
+/// The policy routing rules above every routing table but `local`, the main
+/// table first and default routes highlighted.
+pub fn render_routes<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &NetworkInfo, config: &Config, app: &mut App) {
+    let rules_height = match network_info.rules.len() {
+        0 => 0,
+        rules => rules.min(MAX_RULE_ROWS) as u16 + 3,
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(rules_height), Constraint::Min(0)])
+        .split(area);
+
+    if rules_height > 0 {
+        render_rules(f, chunks[0], network_info, app);
+    }
+
+    let mut routes: Vec<&Route> = network_info.routes.iter().collect();
+    routes.sort_by_key(|route| (!route.in_main_table(), route.table.clone(), route.family.label()));
+    let tables = {
+        let mut tables: Vec<&str> = routes.iter().map(|route| route.table.as_str()).collect();
+        tables.dedup();
+        tables.len()
+    };
+
+    let rows = routes
+        .iter()
+        .map(|route| {
+            let style = match route.is_default() {
+                true => Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+                false => Style::default().fg(Color::White),
+            };
+            let gateway = route.gateway.map_or_else(|| "-".to_string(), |gateway| gateway.to_string());
+            let interface = route.interface.as_deref().map_or_else(|| "-".to_string(), |name| config.display_name(name));
+            let source = route.source.map_or_else(|| "-".to_string(), |source| source.to_string());
+            Row::new(vec![
+                Cell::from(route.to_string()).style(style),
+                Cell::from(gateway).style(Style::default().fg(Color::Cyan)),
+                Cell::from(interface),
+                Cell::from(source).style(Style::default().fg(Color::DarkGray)),
+                Cell::from(route.metric.to_string()),
+                Cell::from(route.table.clone()).style(Style::default().fg(Color::Magenta)),
+            ])
+        })
+        .collect();
+
+    let widths = [
+        Constraint::Percentage(25),
+        Constraint::Percentage(25),
+        Constraint::Min(10),
+        Constraint::Percentage(20),
+        Constraint::Length(6),
+        Constraint::Length(8),
+    ];
+    let table = ViewTable {
+        title: match tables {
+            1 => format!(" Routes ({}) ", routes.len()),
+            tables => format!(" Routes ({} in {} tables) ", routes.len(), tables),
+        },
+        header: Row::new(vec!["Destination", "Gateway", "Interface", "Source", "Metric", "Table"]),
+        rows,
+        widths: &widths,
+        empty: "No routes",
+    };
+    render_table(f, chunks[1], app, table);
+}
+
+/// Policy rules in the order the kernel tries them, IPv4 first, scrolled to
+/// the Rules pane's offset.
+fn render_rules<B: Backend>(f: &mut Frame<B>, area: Rect, network_info: &NetworkInfo, app: &mut App) {
+    let mut rules: Vec<_> = network_info.rules.iter().collect();
+    rules.sort_by_key(|rule| (rule.family.label(), rule.priority));
+
+    // Borders and the header row.
+    let visible = area.height.saturating_sub(3) as usize;
+    app.set_page_size(Pane::Rules, visible);
+    app.rules_scroll = app.rules_scroll.min(rules.len().saturating_sub(visible));
+
+    let rows: Vec<Row> = rules
+        .iter()
+        .skip(app.rules_scroll)
+        .map(|rule| {
+            Row::new(vec![
+                Cell::from(rule.priority.to_string()).style(Style::default().fg(Color::Yellow)),
+                Cell::from(rule.family.label()).style(Style::default().fg(Color::DarkGray)),
+                Cell::from(rule.selector.clone()),
+                Cell::from(rule.action.clone()).style(Style::default().fg(Color::Magenta)),
+            ])
+        })
+        .collect();
+
+    let title = match rules.len() > visible {
+        true => format!(
+            " Policy Rules ({}-{} of {}) ",
+            app.rules_scroll + 1,
+            (app.rules_scroll + visible).min(rules.len()),
+            rules.len()
+        ),
+        false => format!(" Policy Rules ({}) ", rules.len()),
+    };
+    let widths = [
+        Constraint::Length(8),
+        Constraint::Length(6),
+        Constraint::Percentage(60),
+        Constraint::Percentage(30),
+    ];
+    let table = Table::new(rows)
+        .header(Row::new(vec!["Priority", "Family", "Selector", "Action"])
+            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .border_style(pane_style(app, Pane::Rules, Color::Magenta)))
+        .widths(&widths)
+        .column_spacing(2)
+        .style(Style::default().fg(Color::White));
+
+    f.render_widget(table, area);
+}
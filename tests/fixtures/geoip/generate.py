@@ -0,0 +1,104 @@
+#!/usr/bin/env python3
+"""Writes the tiny IPv4-only MaxMind databases used by the geoip tests.
+
+city.mmdb:  198.51.100.0/24 has a country and city, 203.0.113.0/24 only a
+            registered country.
+asn.mmdb:   198.51.100.0/24 and 192.0.2.0/24 have an AS number.
+"""
+import os
+import struct
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+
+def control(kind, size):
+    extra = b""
+    if size >= 29:
+        extra = bytes([size - 29])
+        size = 29
+    if kind <= 7:
+        return bytes([(kind << 5) | size]) + extra
+    return bytes([size, kind - 7]) + extra
+
+
+def encode(value):
+    if isinstance(value, str):
+        data = value.encode()
+        return control(2, len(data)) + data
+    if isinstance(value, dict):
+        return control(7, len(value)) + b"".join(encode(k) + encode(v) for k, v in value.items())
+    if isinstance(value, list):
+        return control(11, len(value)) + b"".join(encode(v) for v in value)
+    if isinstance(value, tuple):
+        kind, number = value
+        data = number.to_bytes((number.bit_length() + 7) // 8, "big") if number else b""
+        return control(kind, len(data)) + data
+    raise TypeError(value)
+
+
+def uint16(number):
+    return (5, number)
+
+
+def uint32(number):
+    return (6, number)
+
+
+def build(name, database_type, networks):
+    nodes = [[None, None]]
+    data = b""
+    for network, record in networks:
+        prefix, length = network.split("/")
+        address = struct.unpack(">I", bytes(map(int, prefix.split("."))))[0]
+        offset = len(data)
+        data += encode(record)
+        node = 0
+        for i in range(int(length)):
+            bit = (address >> (31 - i)) & 1
+            if i == int(length) - 1:
+                nodes[node][bit] = ("data", offset)
+            else:
+                if nodes[node][bit] is None:
+                    nodes.append([None, None])
+                    nodes[node][bit] = len(nodes) - 1
+                node = nodes[node][bit]
+
+    node_count = len(nodes)
+
+    def pointer(entry):
+        if entry is None:
+            return node_count
+        if isinstance(entry, tuple):
+            return node_count + 16 + entry[1]
+        return entry
+
+    tree = b"".join(pointer(left).to_bytes(3, "big") + pointer(right).to_bytes(3, "big") for left, right in nodes)
+    metadata = {
+        "binary_format_major_version": uint16(2),
+        "binary_format_minor_version": uint16(0),
+        "build_epoch": (9, 1700000000),
+        "database_type": database_type,
+        "description": {"en": "myip test fixture"},
+        "ip_version": uint16(4),
+        "languages": ["en"],
+        "node_count": uint32(node_count),
+        "record_size": uint16(24),
+    }
+    with open(os.path.join(HERE, name), "wb") as f:
+        f.write(tree + b"\0" * 16 + data + b"\xab\xcd\xefMaxMind.com" + encode(metadata))
+
+
+build("city.mmdb", "GeoIP2-City", [
+    ("198.51.100.0/24", {
+        "city": {"names": {"en": "Amsterdam"}},
+        "country": {"iso_code": "NL", "names": {"en": "Netherlands"}},
+        "registered_country": {"iso_code": "DE", "names": {"en": "Germany"}},
+    }),
+    ("203.0.113.0/24", {
+        "registered_country": {"iso_code": "US", "names": {"en": "United States"}},
+    }),
+])
+build("asn.mmdb", "GeoLite2-ASN", [
+    ("198.51.100.0/24", {"autonomous_system_number": uint32(64496), "autonomous_system_organization": "Example Transit"}),
+    ("192.0.2.0/24", {"autonomous_system_number": uint32(64511)}),
+])